@@ -1,57 +1,307 @@
 pub const JS: &str = r#"
-const CLIENT_ID = crypto.randomUUID();
+// The first part stays the same on this device, so the history can tell
+// devices apart; the second is new on every page load, so each tab still
+// recognises only its own changes.
+function deviceId() {
+    try {
+        let id = localStorage.getItem('yala-device');
+        if (!id) {
+            id = crypto.randomUUID().slice(0, 8);
+            localStorage.setItem('yala-device', id);
+        }
+        return id;
+    } catch (e) {
+        return crypto.randomUUID().slice(0, 8);
+    }
+}
+const CLIENT_ID = deviceId() + '.' + crypto.randomUUID();
 
 document.body.addEventListener('htmx:configRequest', function(event) {
     event.detail.headers['X-Client-Id'] = CLIENT_ID;
 });
 
 let eventSource = null;
+let lastEventId = null;
 let reconnectAttempts = 0;
 const maxReconnectAttempts = 10;
 
+function pageListId() {
+    var container = document.querySelector('.container[data-list-id]');
+    return container ? container.getAttribute('data-list-id') : null;
+}
+
+function reloadPage() {
+    var listId = pageListId();
+    var path = listId ? '/list/' + encodeURIComponent(listId) : window.location.pathname;
+    htmx.ajax('GET', path, {
+        target: 'body',
+        swap: 'outerHTML'
+    });
+}
+
+function htmlToElement(html) {
+    var template = document.createElement('template');
+    template.innerHTML = html.trim();
+    return template.content.firstElementChild;
+}
+
+// Items back on the list are no longer ones to buy again.
+function forgetBuyAgain(items) {
+    var names = items.map(function(item) { return item.name.toLowerCase(); });
+    document.querySelectorAll('.buy-again-item').forEach(function(el) {
+        if (names.indexOf(el.getAttribute('data-name')) !== -1) {
+            el.remove();
+        }
+    });
+}
+
+function findItem(itemId) {
+    return document.querySelector('.item[data-item-id="' + CSS.escape(itemId) + '"]');
+}
+
+// Lists with categories are rendered in sections, one per category, and
+// every item has to stay in the section of its own category.
+function findSection(category) {
+    return document.querySelector('.section[data-category="' + CSS.escape(category || '') + '"]');
+}
+
+function inOwnSection(el, item) {
+    var section = el.closest('.section');
+    if (!section) {
+        return !item.category;
+    }
+    return section.getAttribute('data-category') === (item.category || '');
+}
+
+function removeItemElement(el) {
+    var section = el.closest('.section');
+    el.remove();
+    if (section && !section.querySelector('.item')) {
+        section.remove();
+    }
+}
+
+let toast = null;
+let toastTimer = null;
+
+function showToast(html) {
+    hideToast();
+    toast = htmlToElement(html);
+    document.body.appendChild(toast);
+    toastTimer = setTimeout(hideToast, 8000);
+}
+
+function hideToast() {
+    clearTimeout(toastTimer);
+    if (toast) {
+        toast.remove();
+        toast = null;
+    }
+}
+
+window.runToastAction = function(url) {
+    hideToast();
+    htmx.ajax('POST', url, {
+        target: 'body',
+        swap: 'outerHTML'
+    });
+};
+
+function applyUpdate(msg) {
+    if (msg.type === 'connected') {
+        return;
+    }
+    if (msg.type === 'toast') {
+        if (msg.list_id === pageListId()) {
+            showToast(msg.html);
+        }
+        return;
+    }
+    if (msg.type === 'resync' || msg.type === 'reload' && !msg.list_id) {
+        reloadPage();
+        return;
+    }
+    if (msg.type === 'reload') {
+        if (msg.list_id === pageListId() || !pageListId()) {
+            reloadPage();
+        }
+        return;
+    }
+
+    var listId = pageListId();
+    if (!listId) {
+        applyHomeUpdate(msg);
+        return;
+    }
+    if (msg.list_id !== listId) {
+        return;
+    }
+
+    var container = document.querySelector('.container');
+    var isHiding = container.getAttribute('data-hide-completed') === 'true';
+    var addItem = document.querySelector('.add-item');
+
+    // While shopping the server decides the order of the sections and which
+    // are collapsed, so the page is fetched again instead of patched.
+    var reorders = ['item_added', 'item_toggled', 'item_renamed', 'item_changed', 'items_sorted'];
+    if (container.hasAttribute('data-shopping') && reorders.indexOf(msg.type) !== -1) {
+        reloadPage();
+        return;
+    }
+
+    switch (msg.type) {
+        case 'item_added':
+            if (document.querySelector('.section') || msg.item.category) {
+                var section = findSection(msg.item.category);
+                if (section) {
+                    section.appendChild(htmlToElement(msg.html));
+                } else {
+                    reloadPage();
+                }
+            } else {
+                addItem.before(htmlToElement(msg.html));
+            }
+            forgetBuyAgain([msg.item]);
+            break;
+        case 'items_added':
+            if (document.querySelector('.section') || msg.items.some(function(item) { return item.category; })) {
+                reloadPage();
+                break;
+            }
+            var added = document.createElement('template');
+            added.innerHTML = msg.html;
+            addItem.before(added.content);
+            forgetBuyAgain(msg.items);
+            break;
+        case 'item_toggled':
+        case 'item_renamed':
+        case 'item_changed':
+            var existing = findItem(msg.item.id);
+            if (!existing) {
+                // A hidden item became visible; its position is unknown here.
+                if (!(isHiding && msg.item.completed)) {
+                    reloadPage();
+                }
+            } else if (existing.contains(document.activeElement)) {
+                // Leave an item alone while it is being edited on this phone.
+            } else if (isHiding && msg.item.completed) {
+                removeItemElement(existing);
+            } else if (!inOwnSection(existing, msg.item)) {
+                reloadPage();
+            } else {
+                existing.replaceWith(htmlToElement(msg.html));
+            }
+            break;
+        case 'item_removed':
+            var removed = findItem(msg.item_id);
+            if (removed) {
+                removeItemElement(removed);
+            }
+            break;
+        case 'items_removed':
+            msg.item_ids.forEach(function(itemId) {
+                var el = findItem(itemId);
+                if (el) {
+                    removeItemElement(el);
+                }
+            });
+            break;
+        case 'items_sorted':
+            // Sorting can change the order of the sections themselves.
+            if (document.querySelector('.section')) {
+                reloadPage();
+                break;
+            }
+            msg.item_ids.forEach(function(itemId) {
+                var el = findItem(itemId);
+                if (el) {
+                    addItem.before(el);
+                }
+            });
+            break;
+        case 'list_renamed':
+            document.querySelector('.header h1').textContent = msg.name;
+            break;
+        case 'list_deleted':
+            window.location.href = '/';
+            return;
+    }
+
+    initializeSwipes();
+}
+
+function applyHomeUpdate(msg) {
+    var entry = document.querySelector('.list-item[data-list-id="' + CSS.escape(msg.list_id) + '"]');
+
+    switch (msg.type) {
+        case 'list_created':
+            var fab = document.querySelector('.fab');
+            if (entry) {
+                return;
+            }
+            if (fab) {
+                fab.before(htmlToElement(msg.html));
+            } else if (document.querySelector('.empty-state')) {
+                reloadPage();
+            }
+            break;
+        case 'list_renamed':
+            if (entry) {
+                entry.querySelector('.list-name').textContent = msg.name;
+            }
+            break;
+        case 'list_deleted':
+            if (entry) {
+                entry.remove();
+                if (!document.querySelector('.list-item')) {
+                    reloadPage();
+                }
+            }
+            break;
+        case 'item_added':
+        case 'items_added':
+        case 'item_removed':
+        case 'items_removed':
+            if (entry) {
+                entry.querySelector('.item-count').textContent = msg.item_count;
+            }
+            break;
+    }
+}
+
 function connectSSE() {
     if (eventSource) {
         eventSource.close();
     }
 
-    eventSource = new EventSource('/events');
+    // Resume after the last update this page has seen; the server replays
+    // what was missed, or asks for a reload if that is too much.
+    var url = '/events';
+    if (lastEventId !== null) {
+        url += '?last_event_id=' + encodeURIComponent(lastEventId);
+    }
+    eventSource = new EventSource(url);
 
     eventSource.onmessage = function(event) {
         if (!event.data || event.data === 'keep-alive') {
             return;
         }
+        if (event.lastEventId) {
+            lastEventId = event.lastEventId;
+        }
 
         try {
             const msg = JSON.parse(event.data);
+            reconnectAttempts = 0;
 
-            if (msg.client_id === CLIENT_ID) {
+            // Toasts are shown to the client that made the change as well.
+            if (msg.client_id === CLIENT_ID && msg.type !== 'toast') {
                 return;
             }
 
-            if (msg.type === 'reload') {
-                const currentPath = window.location.pathname;
-                if (currentPath !== '/') {
-                    htmx.ajax('GET', currentPath, {
-                        target: 'body',
-                        swap: 'outerHTML'
-                    });
-                } else {
-                    htmx.ajax('GET', '/', {
-                        target: 'body',
-                        swap: 'outerHTML'
-                    });
-                }
-            }
-            reconnectAttempts = 0;
+            applyUpdate(msg);
         } catch (e) {
-            // Handle legacy 'reload' string format or parse errors
-            if (event.data === 'reload') {
-                const currentPath = window.location.pathname;
-                htmx.ajax('GET', currentPath, {
-                    target: 'body',
-                    swap: 'outerHTML'
-                });
-            }
+            console.error('Failed to apply update', e);
         }
     };
 
@@ -67,11 +317,6 @@ function connectSSE() {
 document.addEventListener('visibilitychange', function() {
     if (!document.hidden) {
         connectSSE();
-        const currentPath = window.location.pathname;
-        htmx.ajax('GET', currentPath, {
-            target: 'body',
-            swap: 'outerHTML'
-        });
     } else {
         if (eventSource) {
             eventSource.close();
@@ -81,7 +326,7 @@ document.addEventListener('visibilitychange', function() {
 
 connectSSE();
 
-window.handleCheckboxClick = function(event, listId, idx) {
+window.handleCheckboxClick = function(event, listId, itemId) {
     event.preventDefault();
     event.stopPropagation();
 
@@ -108,7 +353,7 @@ window.handleCheckboxClick = function(event, listId, idx) {
                     duration: 300,
                     easing: 'easeInQuad',
                     complete: function() {
-                        htmx.ajax('POST', '/list/' + listId + '/toggle/' + idx, {
+                        htmx.ajax('POST', '/list/' + listId + '/toggle/' + itemId, {
                             target: 'body',
                             swap: 'outerHTML'
                         });
@@ -117,15 +362,76 @@ window.handleCheckboxClick = function(event, listId, idx) {
             }
         });
     } else {
-        htmx.ajax('POST', '/list/' + listId + '/toggle/' + idx, {
+        htmx.ajax('POST', '/list/' + listId + '/toggle/' + itemId, {
             target: 'body',
             swap: 'outerHTML'
         });
     }
 };
 
-window.editItem = function(element, listId, idx) {
-    var itemText = element.textContent;
+window.stepQuantity = function(listId, itemId, direction) {
+    htmx.ajax('POST', '/list/' + listId + '/' + direction + '/' + itemId, {
+        target: 'body',
+        swap: 'outerHTML'
+    });
+};
+
+window.openDetails = function(listId, itemId) {
+    htmx.ajax('GET', '/list/' + listId + '/details/' + itemId, {
+        target: '#details-slot',
+        swap: 'innerHTML'
+    });
+};
+
+window.closeDetails = function() {
+    document.getElementById('details-slot').innerHTML = '';
+};
+
+function previewItems(text) {
+    window.clearSuggestions();
+    htmx.ajax('POST', '/list/' + pageListId() + '/preview', {
+        target: '#details-slot',
+        values: { item: text }
+    });
+}
+
+// Pasting several lines into the add field shows how they are split into
+// items first, instead of adding one item with the whole text.
+document.addEventListener('paste', function(e) {
+    if (e.target.id !== 'add-input') return;
+    var text = (e.clipboardData || window.clipboardData).getData('text');
+    if (!/[\r\n]/.test(text.trim())) return;
+    e.preventDefault();
+    previewItems(e.target.value ? e.target.value + '\n' + text : text);
+});
+
+// Text with commas or semicolons may be several items, or one item like
+// "Tomatoes, canned"; the preview lets the user choose. Decimal commas,
+// as in "1,5 l milk", are added right away.
+document.addEventListener('htmx:beforeRequest', function(e) {
+    var input = document.getElementById('add-input');
+    if (!input || e.detail.elt !== input.form) return;
+    if (!/;|(^|\D),|,(\D|$)/.test(input.value)) return;
+    e.preventDefault();
+    previewItems(input.value);
+});
+
+window.pickSuggestion = function(element) {
+    var input = document.getElementById('add-input');
+    input.value = element.getAttribute('data-value');
+    window.clearSuggestions();
+    htmx.trigger(input.form, 'submit');
+};
+
+window.clearSuggestions = function() {
+    var suggestions = document.getElementById('suggestions');
+    if (suggestions) {
+        suggestions.innerHTML = '';
+    }
+};
+
+window.editItem = function(element, listId, itemId) {
+    var itemText = element.getAttribute('data-edit') || element.textContent;
     var input = document.createElement('input');
     input.type = 'text';
     input.value = itemText;
@@ -139,18 +445,13 @@ window.editItem = function(element, listId, idx) {
     function finishEdit() {
         var newValue = input.value.trim();
         if (newValue && newValue !== itemText) {
-            htmx.ajax('POST', '/list/' + listId + '/edit/' + idx, {
+            htmx.ajax('POST', '/list/' + listId + '/edit/' + itemId, {
                 target: 'body',
                 swap: 'outerHTML',
                 values: {item: newValue}
             });
         } else {
-            var span = document.createElement('span');
-            span.textContent = itemText;
-            span.className = 'item-text';
-            span.style.flex = '1';
-            span.onclick = function() { window.editItem(span, listId, idx); };
-            input.parentNode.replaceChild(span, input);
+            input.parentNode.replaceChild(element, input);
         }
     }
 
@@ -220,13 +521,106 @@ window.handleDeleteCompleted = function(listId) {
     });
 };
 
+// Items are dragged up and down by their handle, within their own section.
+// The item moves through the page as the pointer passes the middle of its
+// neighbours; the new place is sent once it is dropped.
+function initializeDrag(itemElement) {
+    var handle = itemElement.querySelector('.drag-handle');
+    if (!handle) return;
+
+    var neighbour = function(el, direction) {
+        do {
+            el = el[direction];
+        } while (el && !el.classList.contains('item'));
+        return el;
+    };
+
+    var stopTouch = function(e) {
+        // Keep Hammer from seeing the start of the drag.
+        e.stopPropagation();
+    };
+    handle.addEventListener('touchstart', stopTouch);
+    handle.addEventListener('mousedown', stopTouch);
+
+    handle.addEventListener('pointerdown', function(e) {
+        e.preventDefault();
+        e.stopPropagation();
+        handle.setPointerCapture(e.pointerId);
+        itemElement.dragging = true;
+        itemElement.classList.add('dragging');
+        var before = neighbour(itemElement, 'previousElementSibling');
+
+        var move = function(e) {
+            var siblings = Array.prototype.filter.call(itemElement.parentNode.children, function(el) {
+                return el.classList.contains('item') && el !== itemElement;
+            });
+            for (var i = 0; i < siblings.length; i++) {
+                var rect = siblings[i].getBoundingClientRect();
+                if (e.clientY < rect.top + rect.height / 2) {
+                    if (siblings[i] !== itemElement.nextElementSibling) {
+                        siblings[i].before(itemElement);
+                    }
+                    return;
+                }
+            }
+            var last = siblings[siblings.length - 1];
+            if (last && last !== neighbour(itemElement, 'previousElementSibling')) {
+                last.after(itemElement);
+            }
+        };
+
+        var drop = function() {
+            handle.removeEventListener('pointermove', move);
+            handle.removeEventListener('pointerup', drop);
+            handle.removeEventListener('pointercancel', drop);
+            itemElement.dragging = false;
+            itemElement.classList.remove('dragging');
+
+            var after = neighbour(itemElement, 'previousElementSibling');
+            if (after === before) return;
+            var values = after
+                ? { after: after.getAttribute('data-item-id') }
+                : { before: neighbour(itemElement, 'nextElementSibling').getAttribute('data-item-id') };
+            htmx.ajax('POST', '/list/' + pageListId() + '/move/' + itemElement.getAttribute('data-item-id'), {
+                target: 'body',
+                swap: 'outerHTML',
+                values: values
+            });
+        };
+
+        handle.addEventListener('pointermove', move);
+        handle.addEventListener('pointerup', drop);
+        handle.addEventListener('pointercancel', drop);
+    });
+}
+
 function initializeSwipes() {
     document.querySelectorAll('.item').forEach(function(itemElement) {
         if (itemElement.hammerInitialized) return;
         itemElement.hammerInitialized = true;
 
         var hammer = new Hammer(itemElement);
-        hammer.get('pan').set({ direction: Hammer.DIRECTION_HORIZONTAL, threshold: 10 });
+        // Neither gesture may start while the item is dragged by its handle.
+        var notDragging = function() { return !itemElement.dragging; };
+        hammer.get('pan').set({ direction: Hammer.DIRECTION_HORIZONTAL, threshold: 10, enable: notDragging });
+        hammer.get('press').set({ time: 500, enable: notDragging });
+        initializeDrag(itemElement);
+
+        // A long press opens the details sheet. The click that follows when
+        // the finger is lifted must not also start editing the name.
+        var suppressClickUntil = 0;
+        hammer.on('press', function() {
+            window.openDetails(pageListId(), itemElement.getAttribute('data-item-id'));
+        });
+        hammer.on('pressup', function() {
+            suppressClickUntil = Date.now() + 400;
+        });
+        itemElement.addEventListener('click', function(e) {
+            if (Date.now() < suppressClickUntil) {
+                e.stopPropagation();
+                e.preventDefault();
+            }
+        }, true);
 
         var startPos = 0;
         var currentPos = 0;
@@ -280,6 +674,15 @@ function initializeSwipes() {
 }
 
 document.body.addEventListener('htmx:afterSwap', function(e) {
+    // Swapping the body drops an open toast; keep it while the list is shown.
+    if (toast && !document.body.contains(toast)) {
+        if (toast.getAttribute('data-list-id') === pageListId()) {
+            document.body.appendChild(toast);
+        } else {
+            hideToast();
+        }
+    }
+
     var input = document.getElementById('add-input');
     var shouldFocus = false;
 
@@ -197,6 +197,31 @@ button:hover, .btn:hover {
     cursor: grabbing;
 }
 
+.drag-handle {
+    margin: -8px 8px -8px -12px;
+    padding: 8px 4px;
+    color: #4b5563;
+    font-size: 18px;
+    line-height: 1;
+    cursor: grab;
+    flex-shrink: 0;
+    touch-action: none;
+}
+
+.drag-handle:hover {
+    color: #9ca3af;
+}
+
+.item.dragging {
+    background: #1f2937;
+    box-shadow: 0 4px 12px rgba(0, 0, 0, 0.4);
+    z-index: 5;
+}
+
+.item.dragging .drag-handle {
+    cursor: grabbing;
+}
+
 .checkbox {
     width: 24px;
     height: 24px;
@@ -236,6 +261,222 @@ button:hover, .btn:hover {
     color: #f3f4f6;
 }
 
+.section-header {
+    padding: 16px 24px 8px;
+    color: #9ca3af;
+    font-size: 13px;
+    font-weight: 600;
+    letter-spacing: 0.05em;
+    text-transform: uppercase;
+}
+
+.section.done > summary {
+    cursor: pointer;
+    list-style: none;
+}
+
+.section.done > summary::-webkit-details-marker {
+    display: none;
+}
+
+.section.done > summary::before {
+    content: '▸ ';
+}
+
+.section.done[open] > summary::before {
+    content: '▾ ';
+}
+
+.shopping-bar {
+    display: flex;
+    align-items: center;
+    justify-content: space-between;
+    gap: 12px;
+    padding: 12px 24px;
+    background: #1e3a8a;
+    color: #bfdbfe;
+}
+
+.shopping-done {
+    width: auto;
+    padding: 6px 16px;
+    background: #2563eb;
+    color: white;
+}
+
+.store-option {
+    margin-bottom: 8px;
+    background: #374151;
+    color: #f3f4f6;
+}
+
+.store-option.selected {
+    background: #2563eb;
+}
+
+.transfer-option {
+    display: flex;
+    align-items: center;
+    gap: 8px;
+    margin-bottom: 8px;
+}
+
+.transfer-name {
+    flex: 1;
+    min-width: 0;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+}
+
+.template-picker {
+    margin-bottom: 16px;
+}
+
+.template-picker-title {
+    color: #9ca3af;
+    font-size: 14px;
+    margin-bottom: 8px;
+}
+
+.template-option {
+    display: flex;
+    align-items: center;
+    gap: 12px;
+    padding: 12px 16px;
+    margin-bottom: 8px;
+    background: #1f2937;
+    border: 1px solid #374151;
+    border-radius: 8px;
+    cursor: pointer;
+}
+
+.template-option:has(input:checked) {
+    border-color: #2563eb;
+}
+
+.template-name {
+    flex: 1;
+    min-width: 0;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+}
+
+.template-delete {
+    width: auto;
+    padding: 4px 8px;
+    background: none;
+    color: #9ca3af;
+    font-size: 18px;
+}
+
+.template-reset {
+    display: flex;
+    align-items: center;
+    gap: 8px;
+    margin-bottom: 16px;
+    color: #d1d5db;
+}
+
+.store-layout {
+    padding: 16px 24px;
+    border-bottom: 1px solid #1f2937;
+}
+
+.item-main {
+    flex: 1;
+    display: flex;
+    flex-direction: column;
+    min-width: 0;
+}
+
+.item-notes {
+    padding: 0 4px 4px;
+    color: #6b7280;
+    font-size: 13px;
+    overflow: hidden;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+}
+
+.details-btn {
+    width: 28px;
+    height: 28px;
+    padding: 0;
+    margin-left: 4px;
+    flex-shrink: 0;
+    background: transparent;
+    color: #6b7280;
+    font-size: 20px;
+    line-height: 1;
+}
+
+.details-btn:hover {
+    background: #374151;
+    color: #f3f4f6;
+}
+
+.details-row {
+    display: flex;
+    gap: 8px;
+}
+
+textarea {
+    width: 100%;
+    padding: 16px;
+    background: #1f2937;
+    border: 1px solid #374151;
+    border-radius: 8px;
+    color: #f3f4f6;
+    font-family: inherit;
+    font-size: 16px;
+    margin-bottom: 16px;
+    resize: vertical;
+}
+
+textarea:focus {
+    outline: none;
+    border-color: #6b7280;
+}
+
+.quantity {
+    display: flex;
+    align-items: center;
+    gap: 4px;
+    margin-left: 8px;
+    flex-shrink: 0;
+}
+
+.quantity-badge {
+    padding: 2px 8px;
+    border-radius: 999px;
+    background: #1e3a8a;
+    color: #bfdbfe;
+    font-size: 13px;
+    white-space: nowrap;
+}
+
+.quantity-step {
+    width: 28px;
+    height: 28px;
+    padding: 0;
+    border-radius: 50%;
+    background: transparent;
+    color: #6b7280;
+    font-size: 18px;
+    line-height: 1;
+}
+
+.quantity-step:hover {
+    background: #374151;
+    color: #f3f4f6;
+}
+
+.item.completed .quantity {
+    opacity: 0.5;
+}
+
 .add-item {
     display: flex;
     align-items: center;
@@ -270,6 +511,91 @@ button:hover, .btn:hover {
     border-bottom-color: #6b7280;
 }
 
+.suggestions {
+    margin: -8px 24px 8px 60px;
+    border-radius: 8px;
+    overflow: hidden;
+}
+
+.suggestion {
+    padding: 12px 16px;
+    background: #1f2937;
+    border-bottom: 1px solid #374151;
+    cursor: pointer;
+}
+
+.suggestion:last-child {
+    border-bottom: none;
+}
+
+.suggestion:hover {
+    background: #293548;
+}
+
+.buy-again {
+    padding: 0 24px 16px;
+}
+
+.buy-again > summary {
+    padding-left: 0;
+    padding-right: 0;
+    cursor: pointer;
+    list-style: none;
+}
+
+.buy-again > summary::-webkit-details-marker {
+    display: none;
+}
+
+.buy-again-items {
+    display: flex;
+    flex-wrap: wrap;
+    gap: 8px;
+}
+
+.buy-again-item {
+    width: auto;
+    padding: 8px 14px;
+    border-radius: 999px;
+    background: #1f2937;
+    border: 1px solid #374151;
+    color: #f3f4f6;
+    font-size: 14px;
+    font-weight: 400;
+}
+
+.buy-again-amount {
+    margin-left: 6px;
+    color: #9ca3af;
+}
+
+.bulk-items {
+    list-style: none;
+    max-height: 40vh;
+    overflow-y: auto;
+    margin-bottom: 16px;
+}
+
+.bulk-items li {
+    display: flex;
+    justify-content: space-between;
+    align-items: center;
+    gap: 8px;
+    padding: 8px 0;
+    border-bottom: 1px solid #374151;
+}
+
+.bulk-single {
+    margin-top: 8px;
+    background: #374151;
+    color: #f3f4f6;
+}
+
+button:disabled {
+    opacity: 0.5;
+    cursor: default;
+}
+
 .menu {
     position: absolute;
     right: 20px;
@@ -292,6 +618,8 @@ button:hover, .btn:hover {
     gap: 12px;
     transition: background 0.2s;
     font-size: 15px;
+    color: inherit;
+    text-decoration: none;
 }
 
 .menu-item:hover {
@@ -373,6 +701,109 @@ button:hover, .btn:hover {
     margin-bottom: 24px;
 }
 
+.trash-link {
+    display: block;
+    text-align: center;
+    color: #6b7280;
+    text-decoration: none;
+    padding: 16px;
+}
+
+.trash-note {
+    color: #6b7280;
+    margin-bottom: 16px;
+}
+
+.trash-entry {
+    display: flex;
+    align-items: center;
+    gap: 8px;
+    padding: 16px;
+    border-radius: 8px;
+    background: #1f2937;
+    border: 1px solid #374151;
+    margin-bottom: 12px;
+}
+
+.trash-details {
+    display: flex;
+    flex-direction: column;
+    flex: 1;
+    min-width: 0;
+}
+
+.trash-meta {
+    color: #6b7280;
+    font-size: 14px;
+}
+
+.trash-action {
+    width: auto;
+    padding: 8px 12px;
+    background: #374151;
+    color: #f3f4f6;
+    font-size: 14px;
+}
+
+.trash-action.danger {
+    color: #ef4444;
+}
+
+.history-entry {
+    display: flex;
+    justify-content: space-between;
+    align-items: baseline;
+    gap: 12px;
+    padding: 12px 16px;
+    border-bottom: 1px solid #374151;
+    color: inherit;
+    text-decoration: none;
+}
+
+.history-who {
+    color: #9ca3af;
+}
+
+.history-item {
+    display: flex;
+    align-items: center;
+    gap: 12px;
+    padding: 12px 16px;
+}
+
+.history-item.completed .item-text {
+    text-decoration: line-through;
+    color: #6b7280;
+}
+
+.toast {
+    position: fixed;
+    left: 50%;
+    bottom: 30px;
+    transform: translateX(-50%);
+    display: flex;
+    align-items: center;
+    gap: 16px;
+    background: #374151;
+    color: #f3f4f6;
+    border-radius: 8px;
+    padding: 12px 16px;
+    box-shadow: 0 4px 12px rgba(0, 0, 0, 0.4);
+    z-index: 150;
+    white-space: nowrap;
+}
+
+.toast-action {
+    width: auto;
+    padding: 0 4px;
+    background: none;
+    border: none;
+    color: #60a5fa;
+    font-size: 16px;
+    font-weight: 600;
+    cursor: pointer;
+}
+
 ::-webkit-scrollbar {
     width: 8px;
 }
@@ -0,0 +1,116 @@
+//! Splitting pasted or typed text, such as the ingredients of a recipe, into
+//! one entry per item.
+
+/// Splits `input` into item names. Text over several lines gives one item
+/// per line, so "1 onion, chopped" stays together; a single line is split
+/// at commas and semicolons, except for decimal commas as in "1,5 l milk".
+/// Bullets, checkboxes and numbering in front of an entry are dropped, and
+/// so are entries left empty.
+pub fn split(input: &str) -> Vec<String> {
+    let lines: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let entries = match lines.as_slice() {
+        [line] => split_line(line),
+        lines => lines.iter().map(|line| line.to_string()).collect(),
+    };
+    entries
+        .iter()
+        .map(|entry| strip_marker(entry).to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+fn split_line(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut entries = vec![String::new()];
+    for (i, &c) in chars.iter().enumerate() {
+        let between_digits = i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+        if c == ';' || (c == ',' && !between_digits) {
+            entries.push(String::new());
+        } else {
+            entries.last_mut().unwrap().push(c);
+        }
+    }
+    entries
+}
+
+/// Drops list markers from the start of `entry`: "- ", "* ", "• ", "[ ]",
+/// "[x]", and numbering like "1." or "2)", in any combination.
+fn strip_marker(entry: &str) -> &str {
+    let mut entry = entry.trim();
+    loop {
+        let rest = ["-", "*", "+", "•", "–"]
+            .iter()
+            .filter_map(|bullet| entry.strip_prefix(bullet))
+            .find(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            .or_else(|| {
+                ["[ ]", "[x]", "[X]"]
+                    .iter()
+                    .find_map(|checkbox| entry.strip_prefix(checkbox))
+            })
+            .or_else(|| numbering(entry));
+        match rest {
+            Some(rest) => entry = rest.trim_start(),
+            None => return entry,
+        }
+    }
+}
+
+/// What follows numbering such as "1. " or "12) ". A number followed by
+/// anything else, as in "1.5 l" or "2 eggs", is part of the entry.
+fn numbering(entry: &str) -> Option<&str> {
+    let digits = entry.len() - entry.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = entry[digits..]
+        .strip_prefix('.')
+        .or_else(|| entry[digits..].strip_prefix(')'))?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then_some(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_a_single_line_at_commas_and_semicolons() {
+        assert_eq!(split("milk, eggs; bread"), ["milk", "eggs", "bread"]);
+        assert_eq!(split("milk,,eggs,"), ["milk", "eggs"]);
+    }
+
+    #[test]
+    fn keeps_decimal_commas() {
+        assert_eq!(split("1,5 l milk, 2 eggs"), ["1,5 l milk", "2 eggs"]);
+        assert_eq!(split("0,5 kg flour"), ["0,5 kg flour"]);
+    }
+
+    #[test]
+    fn keeps_commas_within_a_line_of_several() {
+        assert_eq!(
+            split("1 onion, chopped\n2 cloves garlic, crushed"),
+            ["1 onion, chopped", "2 cloves garlic, crushed"]
+        );
+    }
+
+    #[test]
+    fn skips_blank_lines() {
+        assert_eq!(split("\nmilk\n\n   \neggs\n"), ["milk", "eggs"]);
+        assert_eq!(split("\n\nTomatoes, canned\n\n"), ["Tomatoes", "canned"]);
+        assert!(split(" \n\n ").is_empty());
+    }
+
+    #[test]
+    fn drops_list_markers() {
+        assert_eq!(
+            split("- milk\n* eggs\n[ ] bread\n[x] butter\n1. flour\n2) sugar\n-"),
+            ["milk", "eggs", "bread", "butter", "flour", "sugar"]
+        );
+        assert_eq!(split("1.5 l milk\n2 eggs"), ["1.5 l milk", "2 eggs"]);
+        assert_eq!(split("-ish\n- - milk"), ["-ish", "milk"]);
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// The category each item name was last filed under, so an item that is
+/// added again lands in the same section without being asked. Names are
+/// matched ignoring case and surrounding whitespace.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct Categories(BTreeMap<String, String>);
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl Categories {
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.0.get(&key(name)).cloned()
+    }
+
+    /// Files `name` under `category`, or forgets it when `category` is
+    /// `None`. Returns whether the mapping changed.
+    pub fn remember(&mut self, name: &str, category: Option<&str>) -> bool {
+        let key = key(name);
+        match category {
+            Some(category) if self.0.get(&key).map(String::as_str) != Some(category) => {
+                self.0.insert(key, category.to_string());
+                true
+            }
+            Some(_) => false,
+            None => self.0.remove(&key).is_some(),
+        }
+    }
+
+    /// Item names, as matched, with their category.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+
+    /// Every category in use, sorted and without duplicates.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.0.values().cloned().collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.dedup();
+        names
+    }
+}
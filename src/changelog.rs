@@ -0,0 +1,329 @@
+use crate::models::{GroceryList, Item};
+use crate::store::Lists;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+/// One change to a list, as appended to the log.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct Change {
+    /// Milliseconds since the Unix epoch.
+    pub at: u64,
+    /// `X-Client-Id` of the client that made the change; empty for API
+    /// callers without one and for edits of the data file.
+    pub client_id: String,
+    pub list_id: String,
+    /// Name of the operation, e.g. `add_item`.
+    pub op: String,
+    /// What the operation was applied to, e.g. the item's name.
+    pub detail: String,
+    pub edit: Edit,
+}
+
+/// What a change did to the list, relative to the version before it.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Edit {
+    /// A list the log did not know yet, in full.
+    Created {
+        list: GroceryList,
+    },
+    Changed {
+        diff: ListDiff,
+    },
+    Deleted,
+}
+
+/// The difference between two versions of a list. Only what changed is
+/// kept, so a change costs about as much as the item it touched.
+#[derive(Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ListDiff {
+    /// The list's own fields, when any of them changed. Its `items` are
+    /// always empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<GroceryList>,
+    /// Items that were added or changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<Item>,
+    /// Ids of the items that were removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+    /// Ids of all items in their new order, when it is not simply the old
+    /// order with new items at the end.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+}
+
+/// Every list as it was at `at`, standing in for all changes up to then.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub at: u64,
+    pub lists: Lists,
+}
+
+/// The part of the log needed to rebuild lists: the snapshot left by the
+/// last compaction, if any, followed by changes made since.
+#[derive(Default)]
+pub struct Changelog {
+    pub snapshot: Option<Snapshot>,
+    pub changes: Vec<Change>,
+}
+
+/// Milliseconds since the Unix epoch.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+impl Edit {
+    /// The edit that turns `old` into `new`; `None` stands for a list that
+    /// does not exist.
+    pub fn between(old: Option<&GroceryList>, new: Option<&GroceryList>) -> Self {
+        match (old, new) {
+            (_, None) => Edit::Deleted,
+            (None, Some(list)) => Edit::Created { list: list.clone() },
+            (Some(old), Some(new)) => Edit::Changed {
+                diff: ListDiff::between(old, new),
+            },
+        }
+    }
+
+    /// Applies the edit to list `id` in `lists`. A diff for a list that is
+    /// not there is dropped.
+    pub fn apply(&self, lists: &mut Lists, id: &str) {
+        match self {
+            Edit::Created { list } => {
+                lists.insert(id.to_string(), list.clone());
+            }
+            Edit::Changed { diff } => {
+                if let Some(list) = lists.get_mut(id) {
+                    diff.apply(list);
+                }
+            }
+            Edit::Deleted => {
+                lists.remove(id);
+            }
+        }
+    }
+}
+
+/// `list` without its items.
+fn fields(list: &GroceryList) -> GroceryList {
+    GroceryList {
+        items: vec![],
+        ..list.clone()
+    }
+}
+
+impl ListDiff {
+    pub fn between(old: &GroceryList, new: &GroceryList) -> Self {
+        let fields = Some(fields(new)).filter(|new| *new != fields(old));
+
+        let new_ids: HashSet<&str> = new.items.iter().map(|item| item.id.as_str()).collect();
+        let removed: Vec<String> = old
+            .items
+            .iter()
+            .filter(|item| !new_ids.contains(item.id.as_str()))
+            .map(|item| item.id.clone())
+            .collect();
+        let items: Vec<Item> = new
+            .items
+            .iter()
+            .filter(|item| !old.items.contains(item))
+            .cloned()
+            .collect();
+
+        // The order `apply` ends up with if it is not told otherwise.
+        let old_ids: HashSet<&str> = old.items.iter().map(|item| item.id.as_str()).collect();
+        let kept = old
+            .items
+            .iter()
+            .filter(|item| new_ids.contains(item.id.as_str()));
+        let added = new
+            .items
+            .iter()
+            .filter(|item| !old_ids.contains(item.id.as_str()));
+        let implied = kept.chain(added).map(|item| item.id.as_str());
+        let order = if implied.eq(new.items.iter().map(|item| item.id.as_str())) {
+            None
+        } else {
+            Some(new.items.iter().map(|item| item.id.clone()).collect())
+        };
+
+        Self {
+            fields,
+            items,
+            removed,
+            order,
+        }
+    }
+
+    pub fn apply(&self, list: &mut GroceryList) {
+        if let Some(fields) = &self.fields {
+            let items = std::mem::take(&mut list.items);
+            *list = GroceryList {
+                items,
+                ..fields.clone()
+            };
+        }
+        list.items.retain(|item| !self.removed.contains(&item.id));
+        for item in &self.items {
+            match list.item_mut(&item.id) {
+                Some(current) => *current = item.clone(),
+                None => list.items.push(item.clone()),
+            }
+        }
+        if let Some(order) = &self.order {
+            list.items.sort_by_key(|item| {
+                order
+                    .iter()
+                    .position(|id| *id == item.id)
+                    .unwrap_or(order.len())
+            });
+        }
+    }
+}
+
+impl Changelog {
+    /// Rebuilds every list as it was at `at`. Before the snapshot nothing is
+    /// known any more and the result is empty.
+    pub fn state_at(&self, at: u64) -> Lists {
+        let mut lists = Lists::new();
+        let mut since = 0;
+        if let Some(snapshot) = &self.snapshot {
+            if snapshot.at > at {
+                return lists;
+            }
+            lists = snapshot.lists.clone();
+            since = snapshot.at;
+        }
+
+        for change in self.changes.iter().filter(|c| c.at > since && c.at <= at) {
+            change.edit.apply(&mut lists, &change.list_id);
+        }
+        lists
+    }
+
+    /// Folds every change made at or before `before` into a new snapshot,
+    /// which replaces the previous one. Returns whether there was anything
+    /// to fold.
+    pub fn compact(&mut self, before: u64) -> bool {
+        let Some(last) = self.changes.iter().rev().find(|c| c.at <= before) else {
+            return false;
+        };
+        let at = last.at;
+        let lists = self.state_at(at);
+        self.snapshot = Some(Snapshot { at, lists });
+        self.changes.retain(|c| c.at > at);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&Item]) -> GroceryList {
+        GroceryList {
+            name: "Groceries".into(),
+            items: items.iter().map(|&item| item.clone()).collect(),
+            show_completed: true,
+            store: None,
+        }
+    }
+
+    /// Logs the change from `old` to `new` at `at`.
+    fn change(at: u64, old: Option<&GroceryList>, new: Option<&GroceryList>) -> Change {
+        Change {
+            at,
+            client_id: String::new(),
+            list_id: "groceries".into(),
+            op: String::new(),
+            detail: String::new(),
+            edit: Edit::between(old, new),
+        }
+    }
+
+    #[test]
+    fn diffs_carry_only_what_changed() {
+        let (milk, eggs, bread) = (
+            Item::new("milk".into()),
+            Item::new("eggs".into()),
+            Item::new("bread".into()),
+        );
+        let old = list(&[&milk, &eggs, &bread]);
+        let mut checked = eggs.clone();
+        checked.completed = true;
+        let butter = Item::new("butter".into());
+        let mut new = list(&[&checked, &milk, &butter]);
+        new.name = "Weekly".into();
+
+        let diff = ListDiff::between(&old, &new);
+        assert_eq!(diff.fields.as_ref().unwrap().name, "Weekly");
+        assert!(diff.fields.as_ref().unwrap().items.is_empty());
+        assert!(diff.items == [checked, butter]);
+        assert_eq!(diff.removed, [bread.id]);
+        assert!(diff.order.is_some());
+
+        let mut rebuilt = old.clone();
+        diff.apply(&mut rebuilt);
+        assert!(rebuilt == new);
+
+        let unchanged = ListDiff::between(&new, &new);
+        assert!(unchanged.fields.is_none() && unchanged.items.is_empty());
+        assert!(unchanged.removed.is_empty() && unchanged.order.is_none());
+    }
+
+    #[test]
+    fn rebuilds_the_lists_at_any_logged_moment() {
+        let (milk, eggs) = (Item::new("milk".into()), Item::new("eggs".into()));
+        let v1 = list(&[&milk]);
+        let v2 = list(&[&milk, &eggs]);
+        let v3 = list(&[&eggs]);
+        let log = Changelog {
+            snapshot: None,
+            changes: vec![
+                change(10, None, Some(&v1)),
+                change(20, Some(&v1), Some(&v2)),
+                change(30, Some(&v2), Some(&v3)),
+                change(40, Some(&v3), None),
+            ],
+        };
+
+        assert!(log.state_at(5).is_empty());
+        assert!(log.state_at(10)["groceries"] == v1);
+        assert!(log.state_at(25)["groceries"] == v2);
+        assert!(log.state_at(30)["groceries"] == v3);
+        assert!(log.state_at(40).is_empty());
+    }
+
+    #[test]
+    fn compaction_keeps_later_states_and_only_the_newest_snapshot() {
+        let (milk, eggs) = (Item::new("milk".into()), Item::new("eggs".into()));
+        let v1 = list(&[&milk]);
+        let v2 = list(&[&milk, &eggs]);
+        let v3 = list(&[&eggs]);
+        let mut log = Changelog {
+            snapshot: Some(Snapshot {
+                at: 5,
+                lists: Lists::new(),
+            }),
+            changes: vec![
+                change(10, None, Some(&v1)),
+                change(20, Some(&v1), Some(&v2)),
+                change(30, Some(&v2), Some(&v3)),
+            ],
+        };
+
+        assert!(log.compact(25));
+        assert_eq!(log.snapshot.as_ref().unwrap().at, 20);
+        assert_eq!(log.changes.len(), 1);
+        assert!(log.state_at(20)["groceries"] == v2);
+        assert!(log.state_at(30)["groceries"] == v3);
+        assert!(log.state_at(10).is_empty());
+
+        assert!(!log.compact(25));
+    }
+}
@@ -0,0 +1,206 @@
+use clap::Parser;
+use serde::Deserialize;
+use std::{io, path::PathBuf, time::Duration};
+
+/// Runtime settings, resolved once at startup.
+///
+/// Every setting can come from a command-line flag, a `YALA_*` environment
+/// variable or the TOML file passed with `--config`. Flags win over
+/// environment variables, which win over the file, which wins over the
+/// built-in defaults.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bind: String,
+    pub data: PathBuf,
+    pub keep_alive: Duration,
+    pub channel_capacity: usize,
+    pub event_backlog: usize,
+    pub watch_debounce: Duration,
+    /// How long deleted lists and items stay in the trash; `None` keeps them
+    /// until they are purged by hand.
+    pub trash_retention: Option<Duration>,
+    /// How long changes stay in the log one by one before they are folded
+    /// into a snapshot; `None` never compacts the log.
+    pub log_retention: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:3000".to_string(),
+            data: PathBuf::from("lists.json"),
+            keep_alive: Duration::from_secs(30),
+            channel_capacity: 100,
+            event_backlog: 500,
+            watch_debounce: Duration::from_millis(100),
+            trash_retention: Some(days(30)),
+            log_retention: Some(days(90)),
+        }
+    }
+}
+
+/// One source of settings. The same struct is filled by clap (flags and
+/// environment) and by serde (config file) so the names cannot drift apart.
+#[derive(Parser, Deserialize, Default)]
+#[command(version, about = "A tiny, mobile-first shared list application")]
+#[serde(default, deny_unknown_fields)]
+struct Layer {
+    /// TOML file to read settings from
+    #[arg(long, env = "YALA_CONFIG")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Address to listen on [default: 0.0.0.0:3000]
+    #[arg(long, env = "YALA_BIND")]
+    bind: Option<String>,
+
+    /// Data file; `.db`, `.sqlite` or `.sqlite3` selects SQLite [default: lists.json]
+    #[arg(long, env = "YALA_DATA")]
+    data: Option<PathBuf>,
+
+    /// Seconds between SSE keep-alive messages [default: 30]
+    #[arg(long, env = "YALA_KEEP_ALIVE_SECS")]
+    keep_alive_secs: Option<u64>,
+
+    /// Number of updates buffered for slow SSE clients [default: 100]
+    #[arg(long, env = "YALA_CHANNEL_CAPACITY")]
+    channel_capacity: Option<usize>,
+
+    /// Number of recent updates kept for reconnecting SSE clients [default: 500]
+    #[arg(long, env = "YALA_EVENT_BACKLOG")]
+    event_backlog: Option<usize>,
+
+    /// Milliseconds to wait for external edits of the data file to settle [default: 100]
+    #[arg(long, env = "YALA_WATCH_DEBOUNCE_MS")]
+    watch_debounce_ms: Option<u64>,
+
+    /// Days before deleted lists and items are purged from the trash; 0 keeps them [default: 30]
+    #[arg(long, env = "YALA_TRASH_RETENTION_DAYS")]
+    trash_retention_days: Option<u64>,
+
+    /// Days before individual changes in the history are folded into a snapshot; 0 keeps them all [default: 90]
+    #[arg(long, env = "YALA_LOG_RETENTION_DAYS")]
+    log_retention_days: Option<u64>,
+}
+
+impl Layer {
+    /// Fills every setting missing from `self` with the one from `other`.
+    fn or(self, other: Layer) -> Layer {
+        Layer {
+            config: self.config.or(other.config),
+            bind: self.bind.or(other.bind),
+            data: self.data.or(other.data),
+            keep_alive_secs: self.keep_alive_secs.or(other.keep_alive_secs),
+            channel_capacity: self.channel_capacity.or(other.channel_capacity),
+            event_backlog: self.event_backlog.or(other.event_backlog),
+            watch_debounce_ms: self.watch_debounce_ms.or(other.watch_debounce_ms),
+            trash_retention_days: self.trash_retention_days.or(other.trash_retention_days),
+            log_retention_days: self.log_retention_days.or(other.log_retention_days),
+        }
+    }
+}
+
+impl Config {
+    /// Reads flags and environment variables, then the config file they point to.
+    pub fn load() -> io::Result<Self> {
+        let args = Layer::parse();
+
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|err| {
+                    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+                })?;
+                toml::from_str(&text).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {}", path.display(), err),
+                    )
+                })?
+            }
+            None => Layer::default(),
+        };
+
+        Self::resolve(args.or(file))
+    }
+
+    /// Checks the merged settings and fills in the defaults.
+    fn resolve(layer: Layer) -> io::Result<Self> {
+        if layer.channel_capacity == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "channel_capacity must be at least 1",
+            ));
+        }
+
+        let defaults = Config::default();
+        Ok(Config {
+            bind: layer.bind.unwrap_or(defaults.bind),
+            data: layer.data.unwrap_or(defaults.data),
+            keep_alive: layer
+                .keep_alive_secs
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.keep_alive),
+            channel_capacity: layer.channel_capacity.unwrap_or(defaults.channel_capacity),
+            event_backlog: layer.event_backlog.unwrap_or(defaults.event_backlog),
+            watch_debounce: layer
+                .watch_debounce_ms
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.watch_debounce),
+            trash_retention: retention(layer.trash_retention_days, defaults.trash_retention),
+            log_retention: retention(layer.log_retention_days, defaults.log_retention),
+        })
+    }
+}
+
+fn days(n: u64) -> Duration {
+    Duration::from_secs(n * 24 * 60 * 60)
+}
+
+/// A retention period given in days, where 0 means forever.
+fn retention(days_setting: Option<u64>, default: Option<Duration>) -> Option<Duration> {
+    match days_setting {
+        Some(0) => None,
+        Some(n) => Some(days(n)),
+        None => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_win_over_environment_which_wins_over_the_file() {
+        // SAFETY: no other test reads these variables.
+        unsafe {
+            std::env::set_var("YALA_BIND", "127.0.0.1:1");
+            std::env::set_var("YALA_KEEP_ALIVE_SECS", "5");
+        }
+        let args = Layer::try_parse_from(["yala", "--bind", "127.0.0.1:2"]).unwrap();
+        unsafe {
+            std::env::remove_var("YALA_BIND");
+            std::env::remove_var("YALA_KEEP_ALIVE_SECS");
+        }
+        let file: Layer =
+            toml::from_str("bind = \"127.0.0.1:3\"\nkeep_alive_secs = 7\nchannel_capacity = 3")
+                .unwrap();
+
+        let config = Config::resolve(args.or(file)).unwrap();
+        assert_eq!(config.bind, "127.0.0.1:2");
+        assert_eq!(config.keep_alive, Duration::from_secs(5));
+        assert_eq!(config.channel_capacity, 3);
+        assert_eq!(config.data, Config::default().data);
+    }
+
+    #[test]
+    fn rejects_a_zero_channel_capacity() {
+        let file: Layer = toml::from_str("channel_capacity = 0").unwrap();
+        assert!(Config::resolve(file).is_err());
+    }
+
+    #[test]
+    fn keeps_the_trash_forever_when_retention_is_zero() {
+        let file: Layer = toml::from_str("trash_retention_days = 0").unwrap();
+        assert_eq!(Config::resolve(file).unwrap().trash_retention, None);
+    }
+}
@@ -0,0 +1,141 @@
+//! Telling when two items are the same thing, so adding "Milk" to a list
+//! that already has "milk" to buy bumps the one it has instead.
+
+use crate::models::Item;
+
+/// Words ending in "s" that are not the plural of the word without it.
+const NOT_PLURAL: &[&str] = &["news", "series", "species", "means"];
+
+/// Whether `a` and `b` name the same thing, ignoring case, spacing and
+/// plurals: "Tomatoes" and "tomato", "cookies" and "Cookie".
+pub fn same_name(a: &str, b: &str) -> bool {
+    let a: Vec<String> = a.split_whitespace().map(str::to_lowercase).collect();
+    let b: Vec<String> = b.split_whitespace().map(str::to_lowercase).collect();
+    a.len() == b.len()
+        && a.iter().zip(&b).all(|(a, b)| {
+            let b = forms(b);
+            forms(a).iter().any(|form| b.contains(form))
+        })
+}
+
+/// `word` and what it would be if it were a plural: "berries" could be
+/// "berrie" or "berry", "tomatoes" "tomatoe" or "tomato". "-es" is only
+/// taken off after "ch", "sh", "x", "z" or "o", so "glasses" is not "glass".
+/// Words ending in "ss", short words and the words in `NOT_PLURAL` are
+/// taken as they are.
+fn forms(word: &str) -> Vec<String> {
+    let mut forms = vec![word.to_string()];
+    if word.chars().count() <= 3 || word.ends_with("ss") || NOT_PLURAL.contains(&word) {
+        return forms;
+    }
+    if let Some(stem) = word.strip_suffix('s') {
+        forms.push(stem.to_string());
+    }
+    if let Some(stem) = word.strip_suffix("es")
+        && ["ch", "sh", "x", "z", "o"]
+            .iter()
+            .any(|end| stem.ends_with(end))
+    {
+        forms.push(stem.to_string());
+    }
+    if let Some(stem) = word.strip_suffix("ies") {
+        forms.push(format!("{}y", stem));
+    }
+    forms
+}
+
+/// Folds `other` into `item` when both are the same thing still to buy.
+/// Amounts in the same unit add up, and an item without a quantity counts
+/// as one; amounts in different units cannot be added, so such items are
+/// not merged. Returns whether `other` was merged.
+pub fn merge(item: &mut Item, other: &Item) -> bool {
+    if item.completed || other.completed || !same_name(&item.name, &other.name) {
+        return false;
+    }
+    let same_unit = match (&item.unit, &other.unit) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        (a, b) => a.is_none() && b.is_none(),
+    };
+    if !same_unit {
+        return false;
+    }
+
+    item.quantity = match (item.quantity, other.quantity) {
+        // Neither says how many: it is simply on the list already.
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(1.0) + b.unwrap_or(1.0)),
+    };
+    if item.notes.is_none() {
+        item.notes = other.notes.clone();
+    }
+    if item.category.is_none() {
+        item.category = other.category.clone();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_case_spacing_and_plurals() {
+        assert!(same_name("Milk", " milk "));
+        assert!(same_name("oat  milk", "Oat Milk"));
+        assert!(same_name("Tomatoes", "tomato"));
+        assert!(same_name("cookies", "Cookie"));
+        assert!(same_name("berries", "berry"));
+        assert!(same_name("eggs", "egg"));
+        assert!(same_name("peaches", "peach"));
+        assert!(same_name("boxes", "box"));
+        assert!(same_name("green apples", "green apple"));
+    }
+
+    fn item(name: &str, quantity: Option<f64>, unit: Option<&str>) -> Item {
+        Item {
+            quantity,
+            unit: unit.map(str::to_string),
+            ..Item::new(name.to_string())
+        }
+    }
+
+    #[test]
+    fn counts_an_item_without_a_quantity_as_one() {
+        let mut milk = item("milk", None, None);
+        assert!(merge(&mut milk, &item("Milk", Some(2.0), None)));
+        assert_eq!(milk.quantity, Some(3.0));
+
+        let mut milk = item("milk", Some(2.0), None);
+        assert!(merge(&mut milk, &item("milk", None, None)));
+        assert_eq!(milk.quantity, Some(3.0));
+
+        let mut milk = item("milk", None, None);
+        assert!(merge(&mut milk, &item("milk", None, None)));
+        assert_eq!(milk.quantity, None);
+    }
+
+    #[test]
+    fn adds_amounts_only_in_the_same_unit() {
+        let mut flour = item("flour", Some(500.0), Some("g"));
+        assert!(merge(&mut flour, &item("Flour", Some(250.0), Some("G"))));
+        assert_eq!(flour.quantity, Some(750.0));
+
+        assert!(!merge(&mut flour, &item("flour", Some(1.0), Some("kg"))));
+        assert!(!merge(&mut flour, &item("flour", None, None)));
+        let mut bare = item("flour", None, None);
+        assert!(!merge(&mut bare, &item("flour", Some(1.0), Some("kg"))));
+        assert_eq!(flour.quantity, Some(750.0));
+        assert_eq!(bare.quantity, None);
+    }
+
+    #[test]
+    fn keeps_different_things_apart() {
+        assert!(!same_name("news", "new"));
+        assert!(!same_name("glasses", "glass"));
+        assert!(!same_name("series", "sery"));
+        assert!(!same_name("glass", "glas"));
+        assert!(!same_name("bus", "bu"));
+        assert!(!same_name("milk", "oat milk"));
+        assert!(!same_name("apple", "apples pie"));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::models::{GroceryList, Item};
+use crate::templates::{home as home_tpl, list as list_tpl};
+use axum::http::HeaderMap;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+
+/// A change pushed to connected clients over SSE.
+///
+/// Clients apply these to the page in place, so a change made on one phone
+/// does not reset input or scroll position on another. Item and list events
+/// carry server-rendered markup so the client never has to duplicate the
+/// templates.
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Update {
+    ItemAdded {
+        list_id: String,
+        item: Item,
+        html: String,
+        item_count: usize,
+    },
+    /// Several items were added at once; `html` holds all of them.
+    ItemsAdded {
+        list_id: String,
+        items: Vec<Item>,
+        html: String,
+        item_count: usize,
+    },
+    ItemToggled {
+        list_id: String,
+        item: Item,
+        html: String,
+    },
+    ItemRenamed {
+        list_id: String,
+        item: Item,
+        html: String,
+    },
+    /// An item's quantity, unit or notes changed.
+    ItemChanged {
+        list_id: String,
+        item: Item,
+        html: String,
+    },
+    ItemRemoved {
+        list_id: String,
+        item_id: String,
+        item_count: usize,
+    },
+    ItemsRemoved {
+        list_id: String,
+        item_ids: Vec<String>,
+        item_count: usize,
+    },
+    /// The items of a list are in a new order, after sorting or after one
+    /// of them was moved.
+    ItemsSorted {
+        list_id: String,
+        item_ids: Vec<String>,
+    },
+    ListCreated {
+        list_id: String,
+        name: String,
+        html: String,
+    },
+    ListRenamed {
+        list_id: String,
+        name: String,
+    },
+    ListDeleted {
+        list_id: String,
+    },
+    /// A short message with an undo or redo button, shown by every client
+    /// that has the list open, including the one that made the change.
+    Toast {
+        list_id: String,
+        html: String,
+    },
+    /// A change that cannot be patched in place. Clients showing the list,
+    /// or every client when `list_id` is `None`, reload the page.
+    Reload {
+        list_id: Option<String>,
+    },
+    /// First message on every SSE connection. Its event id tells the client
+    /// where to resume from if the connection drops.
+    Connected,
+    /// Sent to a single client that missed more updates than the backlog
+    /// holds; it has to reload the page instead of replaying them.
+    Resync,
+}
+
+impl Update {
+    pub fn item_added(list_id: &str, item: Item, item_count: usize) -> Self {
+        Self::ItemAdded {
+            list_id: list_id.to_string(),
+            html: list_tpl::item(list_id, &item).into_string(),
+            item,
+            item_count,
+        }
+    }
+
+    pub fn items_added(list_id: &str, items: &[Item], item_count: usize) -> Self {
+        Self::ItemsAdded {
+            list_id: list_id.to_string(),
+            html: items
+                .iter()
+                .map(|item| list_tpl::item(list_id, item).into_string())
+                .collect(),
+            items: items.to_vec(),
+            item_count,
+        }
+    }
+
+    pub fn item_toggled(list_id: &str, item: Item) -> Self {
+        Self::ItemToggled {
+            list_id: list_id.to_string(),
+            html: list_tpl::item(list_id, &item).into_string(),
+            item,
+        }
+    }
+
+    pub fn item_renamed(list_id: &str, item: Item) -> Self {
+        Self::ItemRenamed {
+            list_id: list_id.to_string(),
+            html: list_tpl::item(list_id, &item).into_string(),
+            item,
+        }
+    }
+
+    pub fn item_changed(list_id: &str, item: Item) -> Self {
+        Self::ItemChanged {
+            list_id: list_id.to_string(),
+            html: list_tpl::item(list_id, &item).into_string(),
+            item,
+        }
+    }
+
+    pub fn toast(list_id: &str, message: &str, action: Action) -> Self {
+        Self::Toast {
+            list_id: list_id.to_string(),
+            html: list_tpl::toast(list_id, message, action).into_string(),
+        }
+    }
+
+    pub fn list_created(list_id: &str, list: &GroceryList) -> Self {
+        Self::ListCreated {
+            list_id: list_id.to_string(),
+            name: list.name.clone(),
+            html: home_tpl::list_entry(list_id, list).into_string(),
+        }
+    }
+
+    /// Serializes the update together with the id of the client that caused
+    /// it, which lets that client skip its own changes.
+    pub fn encode(&self, client_id: &str) -> String {
+        #[derive(Serialize)]
+        struct Envelope<'a> {
+            client_id: &'a str,
+            #[serde(flatten)]
+            update: &'a Update,
+        }
+
+        serde_json::to_string(&Envelope {
+            client_id,
+            update: self,
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// What the button on a toast does.
+#[derive(Clone, Copy)]
+pub enum Action {
+    Undo,
+    Redo,
+}
+
+pub fn client_id(headers: &HeaderMap) -> &str {
+    headers
+        .get("X-Client-Id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// An encoded update together with the id it was sent under.
+#[derive(Clone)]
+pub struct Message {
+    pub id: u64,
+    pub data: String,
+}
+
+/// Fans updates out to SSE clients, numbering them and keeping the most
+/// recent ones so a client that lost its connection can catch up.
+pub struct Broadcaster {
+    tx: broadcast::Sender<Message>,
+    backlog: Mutex<Backlog>,
+}
+
+struct Backlog {
+    next_id: u64,
+    messages: VecDeque<Message>,
+    capacity: usize,
+}
+
+impl Broadcaster {
+    pub fn new(channel_capacity: usize, backlog_capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(channel_capacity);
+        // Ids continue from the clock so they keep increasing across restarts
+        // and ids from before a restart are never mistaken for new ones.
+        let next_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |d| d.as_millis() as u64);
+
+        Self {
+            tx,
+            backlog: Mutex::new(Backlog {
+                next_id,
+                messages: VecDeque::with_capacity(backlog_capacity),
+                capacity: backlog_capacity,
+            }),
+        }
+    }
+
+    pub fn send(&self, data: String) {
+        let mut backlog = self.backlog.lock().unwrap();
+        let message = Message {
+            id: backlog.next_id,
+            data,
+        };
+        backlog.next_id += 1;
+
+        if backlog.capacity > 0 {
+            if backlog.messages.len() == backlog.capacity {
+                backlog.messages.pop_front();
+            }
+            backlog.messages.push_back(message.clone());
+        }
+        // Sent while holding the lock, so a concurrent `subscribe` sees each
+        // message either in the backlog or on its receiver, never both.
+        let _ = self.tx.send(message);
+    }
+
+    /// Subscribes to live messages. Also returns what the client has to
+    /// process first: the messages it missed since `last_id`, followed by a
+    /// `connected` message carrying the current id, or a single `resync`
+    /// message when it missed more than the backlog holds.
+    pub fn subscribe(&self, last_id: Option<u64>) -> (Vec<Message>, broadcast::Receiver<Message>) {
+        let backlog = self.backlog.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let current_id = backlog.next_id - 1;
+
+        let oldest = backlog
+            .messages
+            .front()
+            .map_or(backlog.next_id, |message| message.id);
+        let mut catchup = match last_id {
+            Some(last_id) if last_id > current_id || last_id + 1 < oldest => {
+                return (
+                    vec![Message {
+                        id: current_id,
+                        data: Update::Resync.encode(""),
+                    }],
+                    rx,
+                );
+            }
+            Some(last_id) => backlog
+                .messages
+                .iter()
+                .filter(|message| message.id > last_id)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+        catchup.push(Message {
+            id: current_id,
+            data: Update::Connected.encode(""),
+        });
+        (catchup, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(message: &Message) -> String {
+        let value: serde_json::Value = serde_json::from_str(&message.data).unwrap();
+        value["type"].as_str().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn replays_what_was_missed_since_the_last_id() {
+        let broadcaster = Broadcaster::new(16, 16);
+        broadcaster.send("first".into());
+        let (catchup, _) = broadcaster.subscribe(None);
+        let last_id = catchup[0].id;
+
+        broadcaster.send("second".into());
+        broadcaster.send("third".into());
+        let (catchup, _) = broadcaster.subscribe(Some(last_id));
+
+        let data: Vec<_> = catchup.iter().map(|m| m.data.as_str()).collect();
+        assert_eq!(data[..2], ["second", "third"]);
+        assert_eq!(kind(&catchup[2]), "connected");
+        assert_eq!(catchup[2].id, last_id + 2);
+    }
+
+    #[test]
+    fn asks_for_a_resync_when_the_gap_exceeds_the_backlog() {
+        let broadcaster = Broadcaster::new(16, 2);
+        let (catchup, _) = broadcaster.subscribe(None);
+        let last_id = catchup[0].id;
+
+        for n in 0..3 {
+            broadcaster.send(n.to_string());
+        }
+        let (catchup, _) = broadcaster.subscribe(Some(last_id));
+
+        assert_eq!(catchup.len(), 1);
+        assert_eq!(kind(&catchup[0]), "resync");
+    }
+
+    #[test]
+    fn ids_keep_increasing_across_restarts() {
+        let before = Broadcaster::new(16, 16);
+        before.send("change".into());
+        let (catchup, _) = before.subscribe(None);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        let after = Broadcaster::new(16, 16);
+        after.send("change".into());
+        let (caught_up, _) = after.subscribe(None);
+        assert!(caught_up[0].id > catchup[0].id);
+
+        // Whatever was sent before the restart is gone, so a client
+        // resuming from an old id has to reload.
+        let (catchup, _) = after.subscribe(Some(catchup[0].id));
+        assert_eq!(kind(&catchup[0]), "resync");
+    }
+}
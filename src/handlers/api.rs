@@ -0,0 +1,1088 @@
+use crate::categories::Categories;
+use crate::changelog::Change;
+use crate::events::client_id;
+use crate::models::{
+    AddItemForm, CreateForm, DuplicateList, GroceryList, Item, ItemPatch, ListPatch, ListTemplate,
+    MoveTo, NewStoreLayout, NewTemplate, SortBy, SortParams, StoreLayout, StoreLayoutPatch,
+    SuggestParams, TransferItems, TrashEntry, Trashed,
+};
+use crate::ops::Error;
+use crate::purchases::Purchase;
+use crate::state::AppContext;
+use crate::templates::api_docs;
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{MethodFilter, get, on},
+};
+use serde::Serialize;
+use utoipa::{OpenApi, ToSchema, openapi};
+
+/// Where the API is mounted; also the server URL in the OpenAPI document.
+pub const PREFIX: &str = "/api/v1";
+
+/// Declares the API once: the OpenAPI document and the router are both built
+/// from this list of handlers and their `#[utoipa::path]` attributes, so a
+/// route cannot be served without being documented, or the other way round.
+macro_rules! api {
+    ($($handler:ident),* $(,)?) => {
+        #[derive(OpenApi)]
+        #[openapi(
+            info(title = "yala", description = "JSON API for yala's shared lists."),
+            servers((url = "/api/v1")), // keep in sync with PREFIX
+            paths($($handler),*),
+            components(schemas(
+                GroceryList, Item, CreateForm, AddItemForm, ListPatch, ItemPatch,
+                TrashEntry, Trashed, Change, MoveTo, TransferItems, Purchase, SortBy, Categories, StoreLayout, NewStoreLayout,
+                StoreLayoutPatch, ListTemplate, NewTemplate, DuplicateList, ListResponse, ErrorResponse
+            ))
+        )]
+        pub struct ApiDoc;
+
+        /// Names of the handlers the API is built from, i.e. their operation ids.
+        #[cfg(test)]
+        const HANDLERS: &[&str] = &[$(stringify!($handler)),*];
+
+        /// Routes of the JSON API, to be mounted under `PREFIX`.
+        pub fn routes() -> Router<AppContext> {
+            let spec = ApiDoc::openapi();
+            let mut router = Router::new();
+            $(
+                for (path, method) in operations(&spec, stringify!($handler)) {
+                    router = router.route(&path, on(method, $handler));
+                }
+            )*
+            router
+                .route("/openapi.json", get(openapi_json))
+                .route("/docs", get(docs))
+        }
+    };
+}
+
+api!(
+    get_lists,
+    create_list,
+    get_list,
+    update_list,
+    delete_list,
+    duplicate_list,
+    sort_list,
+    clear_completed,
+    merge_duplicates,
+    move_items,
+    copy_items,
+    undo,
+    redo,
+    add_item,
+    add_items,
+    get_suggestions,
+    get_purchases,
+    buy_again,
+    get_item,
+    update_item,
+    delete_item,
+    toggle_item,
+    move_item,
+    increment_item,
+    decrement_item,
+    get_categories,
+    get_stores,
+    create_store,
+    get_store,
+    update_store,
+    delete_store,
+    get_templates,
+    create_template,
+    get_template,
+    delete_template,
+    get_trash,
+    empty_trash,
+    restore_trash_entry,
+    purge_trash_entry,
+    get_list_history,
+    get_lists_at,
+);
+
+/// Finds the documented path (in axum syntax) and method of a handler. utoipa
+/// uses the function name as operation id.
+fn operations(spec: &openapi::OpenApi, handler: &str) -> Vec<(String, MethodFilter)> {
+    let mut found = vec![];
+    for (path, item) in spec.paths.paths.iter() {
+        let methods = [
+            (&item.get, MethodFilter::GET),
+            (&item.post, MethodFilter::POST),
+            (&item.put, MethodFilter::PUT),
+            (&item.patch, MethodFilter::PATCH),
+            (&item.delete, MethodFilter::DELETE),
+        ];
+        for (operation, method) in methods {
+            if operation
+                .as_ref()
+                .is_some_and(|op| op.operation_id.as_deref() == Some(handler))
+            {
+                found.push((axum_path(path), method));
+            }
+        }
+    }
+    found
+}
+
+/// Converts OpenAPI path parameters (`{id}`) to axum's syntax (`:id`).
+fn axum_path(path: &str) -> String {
+    path.replace('{', ":").replace('}', "")
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListResponse {
+    pub id: String,
+    #[serde(flatten)]
+    pub list: GroceryList,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Error::ListNotFound
+            | Error::ItemNotFound
+            | Error::EntryNotFound
+            | Error::StoreNotFound
+            | Error::TemplateNotFound => StatusCode::NOT_FOUND,
+            Error::EmptyName | Error::InvalidQuantity => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::NothingToUndo | Error::NothingToRedo => StatusCode::CONFLICT,
+            Error::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let body = ErrorResponse {
+            error: self.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, Error>;
+
+/// `201 Created` for a new item, `200 OK` when it was merged into the same
+/// item the list already had.
+fn created_unless(merged: bool) -> StatusCode {
+    if merged {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    }
+}
+
+pub async fn openapi_json() -> Json<openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+pub async fn docs() -> Html<String> {
+    Html(api_docs::render(&ApiDoc::openapi(), PREFIX).into_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists",
+    tag = "lists",
+    responses((status = 200, description = "All lists", body = [ListResponse]))
+)]
+pub async fn get_lists(State(ctx): State<AppContext>) -> Json<Vec<ListResponse>> {
+    let lists = ctx.state.read().unwrap();
+    let mut lists: Vec<_> = lists
+        .iter()
+        .map(|(id, list)| ListResponse {
+            id: id.clone(),
+            list: list.clone(),
+        })
+        .collect();
+    lists.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(lists)
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists",
+    tag = "lists",
+    request_body = CreateForm,
+    responses(
+        (status = 201, description = "List created under a fresh id, with the items of the template if one is given", body = ListResponse),
+        (status = 404, description = "Unknown template", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn create_list(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<CreateForm>,
+) -> ApiResult<(StatusCode, Json<ListResponse>)> {
+    let (id, list) = match body.template.as_deref().filter(|t| !t.is_empty()) {
+        Some(template) => {
+            ctx.create_from_template(
+                client_id(&headers),
+                &body.name,
+                template,
+                body.reset_completed,
+            )
+            .await?
+        }
+        None => ctx.create_list(client_id(&headers), &body.name).await?,
+    };
+    Ok((StatusCode::CREATED, Json(ListResponse { id, list })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "The list", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn get_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.list(&id)?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/lists/{id}",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    request_body = ListPatch,
+    responses(
+        (status = 200, description = "The updated list", body = ListResponse),
+        (status = 404, description = "Unknown list or store", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn update_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(patch): Json<ListPatch>,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.update_list(client_id(&headers), &id, patch).await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{id}",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 204, description = "List deleted"),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn delete_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    ctx.delete_list(client_id(&headers), &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/duplicate",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    request_body = DuplicateList,
+    responses(
+        (status = 201, description = "Copy of the list created under a fresh id, its items with ids of their own", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn duplicate_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<DuplicateList>,
+) -> ApiResult<(StatusCode, Json<ListResponse>)> {
+    let (id, list) = ctx
+        .duplicate_list(
+            client_id(&headers),
+            &id,
+            body.name.as_deref(),
+            body.reset_completed,
+        )
+        .await?;
+    Ok((StatusCode::CREATED, Json(ListResponse { id, list })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/sort",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id"), SortParams),
+    responses(
+        (status = 200, description = "The list, items sorted A-Z or by category", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn sort_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<SortParams>,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.sort_list(client_id(&headers), &id, params.by).await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/clear-completed",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "The removed items", body = [Item]),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn clear_completed(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Vec<Item>>> {
+    let removed = ctx.clear_completed(client_id(&headers), &id).await?;
+    Ok(Json(removed))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/merge-duplicates",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "The list with items still to buy that are the same thing, ignoring case and plurals, merged into the first of them", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn merge_duplicates(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.merge_duplicates(client_id(&headers), &id).await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/move-items",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    request_body = TransferItems,
+    responses(
+        (status = 200, description = "The items, now at the end of the other list under ids of their own", body = [Item]),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn move_items(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<TransferItems>,
+) -> ApiResult<Json<Vec<Item>>> {
+    let items = ctx
+        .transfer_items(client_id(&headers), &id, &body.to, &body.items, false)
+        .await?;
+    Ok(Json(items))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/copy-items",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    request_body = TransferItems,
+    responses(
+        (status = 200, description = "The copies, with ids of their own, at the end of the other list", body = [Item]),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn copy_items(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<TransferItems>,
+) -> ApiResult<Json<Vec<Item>>> {
+    let items = ctx
+        .transfer_items(client_id(&headers), &id, &body.to, &body.items, true)
+        .await?;
+    Ok(Json(items))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/undo",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "The list with its latest change reverted", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 409, description = "Nothing to undo", body = ErrorResponse)
+    )
+)]
+pub async fn undo(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.undo(client_id(&headers), &id).await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/redo",
+    tag = "lists",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "The list with its latest undone change applied again", body = ListResponse),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 409, description = "Nothing to redo", body = ErrorResponse)
+    )
+)]
+pub async fn redo(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx.redo(client_id(&headers), &id).await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items",
+    tag = "items",
+    params(("id" = String, Path, description = "List id")),
+    request_body = AddItemForm,
+    responses(
+        (status = 201, description = "Item added to the end of the list; a quantity in the name (\"2x milk\", \"500 g flour\") is split off", body = Item),
+        (status = 200, description = "The list already had the item to buy, ignoring case and plurals; it was merged into that one and their amounts added up", body = Item),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn add_item(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<AddItemForm>,
+) -> ApiResult<(StatusCode, Json<Item>)> {
+    let (item, merged) = ctx.add_item(client_id(&headers), &id, &body.item).await?;
+    Ok((created_unless(merged), Json(item)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items/bulk",
+    tag = "items",
+    params(("id" = String, Path, description = "List id")),
+    request_body(content = AddItemForm, description = "One item per line, or several on one line separated by commas; bullets, checkboxes and numbering are dropped"),
+    responses(
+        (status = 201, description = "Items added to the end of the list in one change", body = [Item]),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 422, description = "No items in the text", body = ErrorResponse)
+    )
+)]
+pub async fn add_items(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<AddItemForm>,
+) -> ApiResult<(StatusCode, Json<Vec<Item>>)> {
+    let items = ctx.add_items(client_id(&headers), &id, &body.item).await?;
+    Ok((StatusCode::CREATED, Json(items)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/items/{item}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    responses(
+        (status = 200, description = "The item", body = Item),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn get_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<Item>> {
+    let list = ctx.list(&id)?;
+    list.items
+        .into_iter()
+        .find(|item| item.id == item_id)
+        .map(Json)
+        .ok_or(Error::ItemNotFound)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/lists/{id}/items/{item}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    request_body = ItemPatch,
+    responses(
+        (status = 200, description = "The updated item", body = Item),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse),
+        (status = 422, description = "Empty name or quantity not positive", body = ErrorResponse)
+    )
+)]
+pub async fn update_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(patch): Json<ItemPatch>,
+) -> ApiResult<Json<Item>> {
+    let item = ctx
+        .update_item(client_id(&headers), &id, &item_id, patch)
+        .await?;
+    Ok(Json(item))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lists/{id}/items/{item}",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    responses(
+        (status = 204, description = "Item deleted"),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn delete_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    ctx.delete_item(client_id(&headers), &id, &item_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items/{item}/toggle",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    responses(
+        (status = 200, description = "The item with its completion flipped", body = Item),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn toggle_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Item>> {
+    let item = ctx.toggle_item(client_id(&headers), &id, &item_id).await?;
+    Ok(Json(item))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items/{item}/move",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    request_body(content = MoveTo, description = "One of `{\"position\": 0}`, `{\"before\": \"<item id>\"}` or `{\"after\": \"<item id>\"}`"),
+    responses(
+        (status = 200, description = "The list in its new order", body = ListResponse),
+        (status = 404, description = "Unknown list, or unknown item to move or to move next to", body = ErrorResponse)
+    )
+)]
+pub async fn move_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(to): Json<MoveTo>,
+) -> ApiResult<Json<ListResponse>> {
+    let list = ctx
+        .move_item(client_id(&headers), &id, &item_id, to)
+        .await?;
+    Ok(Json(ListResponse { id, list }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items/{item}/increment",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    responses(
+        (status = 200, description = "The item with its quantity raised by one step of its unit", body = Item),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn increment_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Item>> {
+    let item = ctx
+        .step_quantity(client_id(&headers), &id, &item_id, true)
+        .await?;
+    Ok(Json(item))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/items/{item}/decrement",
+    tag = "items",
+    params(
+        ("id" = String, Path, description = "List id"),
+        ("item" = String, Path, description = "Item id")
+    ),
+    responses(
+        (status = 200, description = "The item with its quantity lowered by one step of its unit, but not below one step", body = Item),
+        (status = 404, description = "Unknown list or item", body = ErrorResponse)
+    )
+)]
+pub async fn decrement_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<Item>> {
+    let item = ctx
+        .step_quantity(client_id(&headers), &id, &item_id, false)
+        .await?;
+    Ok(Json(item))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/suggestions",
+    tag = "items",
+    params(("id" = String, Path, description = "List id"), SuggestParams),
+    responses(
+        (status = 200, description = "Item names added before that match `q`, best first; names the list has unchecked are left out", body = [String]),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn get_suggestions(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<SuggestParams>,
+) -> ApiResult<Json<Vec<String>>> {
+    Ok(Json(ctx.suggestions(&id, &params.q)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/purchases",
+    tag = "items",
+    params(("id" = String, Path, description = "List id")),
+    responses(
+        (status = 200, description = "Items checked off or cleared from the list, most recent first, one per name", body = [Purchase]),
+        (status = 404, description = "Unknown list", body = ErrorResponse)
+    )
+)]
+pub async fn get_purchases(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<Vec<Purchase>>> {
+    Ok(Json(ctx.purchases(&id)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lists/{id}/buy-again",
+    tag = "items",
+    params(("id" = String, Path, description = "List id")),
+    request_body(content = AddItemForm, description = "Name of an item bought from the list before"),
+    responses(
+        (status = 201, description = "Item added to the end of the list with the quantity, unit and category it was bought with", body = Item),
+        (status = 200, description = "The list already had the item to buy; it was merged into that one", body = Item),
+        (status = 404, description = "Unknown list, or nothing of that name was bought from it", body = ErrorResponse)
+    )
+)]
+pub async fn buy_again(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(body): Json<AddItemForm>,
+) -> ApiResult<(StatusCode, Json<Item>)> {
+    let (item, merged) = ctx.buy_again(client_id(&headers), &id, &body.item).await?;
+    Ok((created_unless(merged), Json(item)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "items",
+    responses((status = 200, description = "The category each item name was last filed under, by lowercase name", body = Categories))
+)]
+pub async fn get_categories(State(ctx): State<AppContext>) -> Json<Categories> {
+    Json(ctx.categories.lock().unwrap().clone())
+}
+
+#[utoipa::path(
+    get,
+    path = "/stores",
+    tag = "stores",
+    responses((status = 200, description = "All store layouts", body = [StoreLayout]))
+)]
+pub async fn get_stores(State(ctx): State<AppContext>) -> Json<Vec<StoreLayout>> {
+    Json(ctx.store_layouts())
+}
+
+#[utoipa::path(
+    post,
+    path = "/stores",
+    tag = "stores",
+    request_body = NewStoreLayout,
+    responses(
+        (status = 201, description = "Store layout created", body = StoreLayout),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn create_store(
+    State(ctx): State<AppContext>,
+    Json(body): Json<NewStoreLayout>,
+) -> ApiResult<(StatusCode, Json<StoreLayout>)> {
+    let layout = ctx.create_store_layout(body).await?;
+    Ok((StatusCode::CREATED, Json(layout)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stores/{store}",
+    tag = "stores",
+    params(("store" = String, Path, description = "Store layout id")),
+    responses(
+        (status = 200, description = "The store layout", body = StoreLayout),
+        (status = 404, description = "Unknown store", body = ErrorResponse)
+    )
+)]
+pub async fn get_store(
+    Path(store_id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<StoreLayout>> {
+    Ok(Json(ctx.store_layout(&store_id)?))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/stores/{store}",
+    tag = "stores",
+    params(("store" = String, Path, description = "Store layout id")),
+    request_body = StoreLayoutPatch,
+    responses(
+        (status = 200, description = "The updated store layout", body = StoreLayout),
+        (status = 404, description = "Unknown store", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn update_store(
+    Path(store_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(patch): Json<StoreLayoutPatch>,
+) -> ApiResult<Json<StoreLayout>> {
+    let layout = ctx
+        .update_store_layout(client_id(&headers), &store_id, patch)
+        .await?;
+    Ok(Json(layout))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/stores/{store}",
+    tag = "stores",
+    params(("store" = String, Path, description = "Store layout id")),
+    responses(
+        (status = 204, description = "Store layout deleted; lists shopped there no longer have a store"),
+        (status = 404, description = "Unknown store", body = ErrorResponse)
+    )
+)]
+pub async fn delete_store(
+    Path(store_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<StatusCode> {
+    ctx.delete_store_layout(client_id(&headers), &store_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/templates",
+    tag = "templates",
+    responses((status = 200, description = "All list templates, in the order they were saved", body = [ListTemplate]))
+)]
+pub async fn get_templates(State(ctx): State<AppContext>) -> Json<Vec<ListTemplate>> {
+    Json(ctx.templates())
+}
+
+#[utoipa::path(
+    post,
+    path = "/templates",
+    tag = "templates",
+    request_body = NewTemplate,
+    responses(
+        (status = 201, description = "Template saved with the items of the list, replacing a template of the same name", body = ListTemplate),
+        (status = 404, description = "Unknown list", body = ErrorResponse),
+        (status = 422, description = "Empty name", body = ErrorResponse)
+    )
+)]
+pub async fn create_template(
+    State(ctx): State<AppContext>,
+    Json(body): Json<NewTemplate>,
+) -> ApiResult<(StatusCode, Json<ListTemplate>)> {
+    let template = ctx.save_template(&body.list, body.name.as_deref()).await?;
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/templates/{template}",
+    tag = "templates",
+    params(("template" = String, Path, description = "Template id")),
+    responses(
+        (status = 200, description = "The template", body = ListTemplate),
+        (status = 404, description = "Unknown template", body = ErrorResponse)
+    )
+)]
+pub async fn get_template(
+    Path(template_id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<ListTemplate>> {
+    Ok(Json(ctx.template(&template_id)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/templates/{template}",
+    tag = "templates",
+    params(("template" = String, Path, description = "Template id")),
+    responses(
+        (status = 204, description = "Template deleted; lists created from it are kept"),
+        (status = 404, description = "Unknown template", body = ErrorResponse)
+    )
+)]
+pub async fn delete_template(
+    Path(template_id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<StatusCode> {
+    ctx.delete_template(&template_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/trash",
+    tag = "trash",
+    responses((status = 200, description = "Deleted lists and items, most recent first", body = [TrashEntry]))
+)]
+pub async fn get_trash(State(ctx): State<AppContext>) -> Json<Vec<TrashEntry>> {
+    Json(ctx.trash())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash",
+    tag = "trash",
+    responses((status = 204, description = "Trash emptied"))
+)]
+pub async fn empty_trash(State(ctx): State<AppContext>) -> StatusCode {
+    ctx.empty_trash().await;
+    StatusCode::NO_CONTENT
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/{entry}/restore",
+    tag = "trash",
+    params(("entry" = String, Path, description = "Trash entry id")),
+    responses(
+        (status = 200, description = "The restored entry; `list_id` is where it was put back", body = TrashEntry),
+        (status = 404, description = "Unknown entry, or the item's list no longer exists", body = ErrorResponse)
+    )
+)]
+pub async fn restore_trash_entry(
+    Path(entry_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> ApiResult<Json<TrashEntry>> {
+    let entry = ctx.restore(client_id(&headers), &entry_id).await?;
+    Ok(Json(entry))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/{entry}",
+    tag = "trash",
+    params(("entry" = String, Path, description = "Trash entry id")),
+    responses(
+        (status = 204, description = "Entry deleted for good"),
+        (status = 404, description = "Unknown entry", body = ErrorResponse)
+    )
+)]
+pub async fn purge_trash_entry(
+    Path(entry_id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<StatusCode> {
+    ctx.purge(&entry_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/lists/{id}/history",
+    tag = "history",
+    params(("id" = String, Path, description = "List id; deleted lists keep their history")),
+    responses(
+        (status = 200, description = "Changes to the list, most recent first", body = [Change]),
+        (status = 500, description = "The log could not be read", body = ErrorResponse)
+    )
+)]
+pub async fn get_list_history(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<Vec<Change>>> {
+    Ok(Json(ctx.changes_to(&id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/history/{at}",
+    tag = "history",
+    params(("at" = u64, Path, description = "Milliseconds since the Unix epoch")),
+    responses(
+        (status = 200, description = "All lists as they were at that moment, rebuilt from the log", body = [ListResponse]),
+        (status = 500, description = "The log could not be read", body = ErrorResponse)
+    )
+)]
+pub async fn get_lists_at(
+    Path(at): Path<u64>,
+    State(ctx): State<AppContext>,
+) -> ApiResult<Json<Vec<ListResponse>>> {
+    let mut lists: Vec<_> = ctx
+        .lists_at(at)
+        .await?
+        .into_iter()
+        .map(|(id, list)| ListResponse { id, list })
+        .collect();
+    lists.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(Json(lists))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::store::JsonStore;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[test]
+    fn every_handler_is_documented() {
+        let spec = ApiDoc::openapi();
+        for handler in HANDLERS {
+            assert!(
+                !operations(&spec, handler).is_empty(),
+                "{handler} has no operation in the OpenAPI document"
+            );
+        }
+    }
+
+    /// Sends a request for every documented operation and checks that the
+    /// router knows it: axum answers unknown paths with an empty 404 and
+    /// unknown methods with 405, while our handlers always answer in JSON.
+    #[tokio::test]
+    async fn router_serves_every_documented_operation() {
+        let dir = std::env::temp_dir().join(format!("yala-api-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = Arc::new(JsonStore::new(
+            dir.join("lists.json"),
+            Config::default().watch_debounce,
+        ));
+        let ctx = AppContext::with_store(store, Config::default())
+            .await
+            .unwrap();
+        let app = routes().with_state(ctx);
+
+        let spec = ApiDoc::openapi();
+        for (path, item) in spec.paths.paths.iter() {
+            let methods = [
+                ("GET", &item.get),
+                ("POST", &item.post),
+                ("PUT", &item.put),
+                ("PATCH", &item.patch),
+                ("DELETE", &item.delete),
+            ];
+            for (method, operation) in methods {
+                let Some(operation) = operation else { continue };
+                let uri = path
+                    .replace("{id}", "missing")
+                    .replace("{item}", "missing")
+                    .replace("{entry}", "missing")
+                    .replace("{store}", "missing")
+                    .replace("{template}", "missing")
+                    .replace("{at}", "0");
+                let mut request = Request::builder().method(method).uri(&uri);
+                let body = if operation.request_body.is_some() {
+                    request = request.header("content-type", "application/json");
+                    Body::from("{}")
+                } else {
+                    Body::empty()
+                };
+
+                let response = app
+                    .clone()
+                    .oneshot(request.body(body).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                assert_ne!(
+                    status,
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    "{method} {path} is not routed"
+                );
+                assert!(
+                    status != StatusCode::NOT_FOUND || body.starts_with(b"{\"error\""),
+                    "{method} {path} is not routed"
+                );
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
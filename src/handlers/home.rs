@@ -1,9 +1,10 @@
-use crate::models::{CreateForm, GroceryList};
-use crate::state::{AppContext, save_data};
+use crate::events::client_id;
+use crate::models::CreateForm;
+use crate::state::AppContext;
 use crate::templates::{home as home_tpl, layout};
 use axum::{
     Form,
-    extract::State,
+    extract::{Path, State},
     http::HeaderMap,
     response::{Html, IntoResponse},
 };
@@ -14,8 +15,8 @@ pub async fn home(State(ctx): State<AppContext>) -> Html<String> {
     Html(layout::render(content).into_string())
 }
 
-pub async fn new_list_form() -> Html<String> {
-    let content = home_tpl::new_list_form();
+pub async fn new_list_form(State(ctx): State<AppContext>) -> Html<String> {
+    let content = home_tpl::new_list_form(&ctx.templates());
     Html(layout::render(content).into_string())
 }
 
@@ -30,27 +31,25 @@ pub async fn create_list(
     headers: HeaderMap,
     Form(form): Form<CreateForm>,
 ) -> impl IntoResponse {
-    let id = form.name.to_lowercase().replace(" ", "-");
-    let list = GroceryList {
-        name: form.name,
-        items: vec![],
-        show_completed: true,
+    let _ = match form.template.as_deref().filter(|t| !t.is_empty()) {
+        Some(template) => {
+            ctx.create_from_template(
+                client_id(&headers),
+                &form.name,
+                template,
+                form.reset_completed,
+            )
+            .await
+        }
+        None => ctx.create_list(client_id(&headers), &form.name).await,
     };
-
-    ctx.state.write().unwrap().insert(id.clone(), list);
-    save_data(&ctx.state).await;
-
-    let client_id = headers
-        .get("X-Client-Id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("")
-        .to_string();
-
-    let event = serde_json::json!({
-        "type": "reload",
-        "client_id": client_id
-    });
-    let _ = ctx.update_tx.send(event.to_string());
-
     lists_view(State(ctx)).await
 }
+
+pub async fn delete_template(
+    Path(template_id): Path<String>,
+    State(ctx): State<AppContext>,
+) -> impl IntoResponse {
+    let _ = ctx.delete_template(&template_id).await;
+    new_list_form(State(ctx)).await
+}
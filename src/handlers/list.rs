@@ -1,98 +1,281 @@
-use crate::models::{AddItemForm, Item};
-use crate::state::{AppContext, save_data};
-use crate::templates::{layout, list as list_tpl};
+use crate::events::client_id;
+use crate::models::{
+    AddItemForm, DuplicateList, ItemDetailsForm, ItemPatch, ListPatch, MoveForm, MoveTo,
+    RenameListForm, SaveTemplateForm, SelectStoreForm, SortParams, TransferForm,
+};
+use crate::quantity;
+use crate::state::AppContext;
+use crate::templates::{history as history_tpl, layout, list as list_tpl};
 use axum::{
     Form,
-    extract::{Path, State},
-    http::HeaderMap,
-    response::{Html, IntoResponse},
+    extract::{Path, Query, State},
+    http::{HeaderMap, header},
+    response::{Html, IntoResponse, Response},
 };
+use maud::html;
+use serde::Deserialize;
 
-fn broadcast_update(ctx: &AppContext, headers: &HeaderMap) {
-    let client_id = headers
-        .get("X-Client-Id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("")
-        .to_string();
-
-    let event = serde_json::json!({
-        "type": "reload",
-        "client_id": client_id
-    });
-    let _ = ctx.update_tx.send(event.to_string());
+pub async fn view_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    render_list(&ctx, &id, shopping(&headers))
 }
 
-pub async fn view_list(Path(id): Path<String>, State(ctx): State<AppContext>) -> impl IntoResponse {
+fn render_list(ctx: &AppContext, id: &str, shopping: bool) -> Html<String> {
+    let layouts = ctx.store_layouts();
+    let purchases = ctx.purchases(id).unwrap_or_default();
     let lists = ctx.state.read().unwrap();
-    let Some(list) = lists.get(&id) else {
+    let Some(list) = lists.get(id) else {
         return Html(layout::render(maud::html! { "List not found" }).into_string());
     };
 
-    let content = list_tpl::render(&id, list);
+    let content = list_tpl::render(id, list, &layouts, &purchases, shopping);
     Html(layout::render(content).into_string())
 }
 
+/// Shopping mode belongs to the phone, not the list, so it is kept in a
+/// cookie scoped to the list's URL.
+fn shopping(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .any(|cookie| cookie.trim() == "shopping=1")
+}
+
+/// Turns shopping mode on or off for this phone. It ends by itself after
+/// twelve hours, so nobody finds the list in shop order the next week.
+pub async fn toggle_shopping(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let on = !shopping(&headers);
+    let cookie = if on {
+        format!("shopping=1; Path=/list/{}; Max-Age=43200; SameSite=Lax", id)
+    } else {
+        format!("shopping=; Path=/list/{}; Max-Age=0; SameSite=Lax", id)
+    };
+    ([(header::SET_COOKIE, cookie)], render_list(&ctx, &id, on))
+}
+
+pub async fn select_store(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<SelectStoreForm>,
+) -> impl IntoResponse {
+    let patch = ListPatch {
+        store: Some(Some(form.store).filter(|store| !store.is_empty())),
+        ..Default::default()
+    };
+    let _ = ctx.update_list(client_id(&headers), &id, patch).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
 pub async fn add_item(
     Path(id): Path<String>,
     State(ctx): State<AppContext>,
     headers: HeaderMap,
     Form(form): Form<AddItemForm>,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        list.items.push(Item {
-            name: form.item,
-            completed: false,
-        });
-    }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+    let _ = ctx.add_item(client_id(&headers), &id, &form.item).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// Adds the items of text checked in the preview.
+pub async fn add_items(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<AddItemForm>,
+) -> impl IntoResponse {
+    let _ = ctx.add_items(client_id(&headers), &id, &form.item).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// The items pasted text, or text with commas, would add, shown in
+/// `#details-slot` to check before adding them.
+pub async fn preview_items(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    Form(form): Form<AddItemForm>,
+) -> Html<String> {
+    let items = ctx.parse_items(&form.item);
+    Html(list_tpl::bulk_preview(&id, &form.item, &items).into_string())
+}
+
+pub async fn buy_again(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<AddItemForm>,
+) -> impl IntoResponse {
+    let _ = ctx.buy_again(client_id(&headers), &id, &form.item).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// Names to finish what is typed in the add field with, shown in
+/// `#suggestions`.
+pub async fn suggestions(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    Query(form): Query<AddItemForm>,
+) -> Html<String> {
+    let names = ctx.suggestions(&id, &form.item).unwrap_or_default();
+    // Picking a suggestion keeps the quantity typed in front of the name.
+    let parsed = quantity::parse(&form.item);
+    let amount = parsed
+        .quantity
+        .map(|q| quantity::format(q, parsed.unit.as_deref()));
+    Html(list_tpl::suggestions(&names, amount.as_deref()).into_string())
 }
 
 pub async fn edit_item(
-    Path((id, idx)): Path<(String, usize)>,
+    Path((id, item_id)): Path<(String, String)>,
     State(ctx): State<AppContext>,
     headers: HeaderMap,
     Form(form): Form<AddItemForm>,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        if let Some(item) = list.items.get_mut(idx) {
-            item.name = form.item;
-        }
+    // The edit field shows the quantity in front of the name, so it is
+    // parsed again just like the quick-add input.
+    let parsed = quantity::parse(&form.item);
+    let patch = ItemPatch {
+        name: Some(parsed.name),
+        quantity: Some(parsed.quantity),
+        unit: Some(parsed.unit),
+        ..Default::default()
+    };
+    let _ = ctx
+        .update_item(client_id(&headers), &id, &item_id, patch)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// The details sheet of an item, as a fragment for `#details-slot`.
+pub async fn item_details(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+) -> Html<String> {
+    let item = ctx
+        .list(&id)
+        .ok()
+        .and_then(|list| list.items.into_iter().find(|item| item.id == item_id));
+    match item {
+        Some(item) => Html(list_tpl::details(&id, &item, &ctx.categories()).into_string()),
+        None => Html(String::new()),
     }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+}
+
+/// The lists an item can be moved or copied to, shown in `#details-slot`.
+pub async fn transfer_picker(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+) -> Html<String> {
+    let lists = ctx.state.read().unwrap();
+    let Some(item) = lists.get(&id).and_then(|list| list.item(&item_id)) else {
+        return Html(String::new());
+    };
+    let mut others: Vec<(&String, &String)> = lists
+        .iter()
+        .filter(|(other, _)| **other != id)
+        .map(|(other, list)| (other, &list.name))
+        .collect();
+    others.sort_by_key(|(_, name)| name.to_lowercase());
+    Html(list_tpl::transfer_picker(&id, item, &others).into_string())
+}
+
+pub async fn move_to(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<TransferForm>,
+) -> impl IntoResponse {
+    let _ = ctx
+        .transfer_items(client_id(&headers), &id, &form.to, &[item_id], false)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn copy_to(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<TransferForm>,
+) -> impl IntoResponse {
+    let _ = ctx
+        .transfer_items(client_id(&headers), &id, &form.to, &[item_id], true)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn save_item_details(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<ItemDetailsForm>,
+) -> impl IntoResponse {
+    // Anything that is not a number is passed on as NaN and rejected along
+    // with zero and negative quantities.
+    let quantity = match form.quantity.trim() {
+        "" => None,
+        text => Some(text.replace(',', ".").parse().unwrap_or(f64::NAN)),
+    };
+    let patch = ItemPatch {
+        name: Some(form.name),
+        quantity: Some(quantity),
+        unit: Some(Some(form.unit)),
+        notes: Some(Some(form.notes)),
+        category: Some(Some(form.category)),
+        ..Default::default()
+    };
+    let _ = ctx
+        .update_item(client_id(&headers), &id, &item_id, patch)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
 }
 
 pub async fn delete_item(
-    Path((id, idx)): Path<(String, usize)>,
+    Path((id, item_id)): Path<(String, String)>,
     State(ctx): State<AppContext>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        if idx < list.items.len() {
-            list.items.remove(idx);
-        }
-    }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+    let _ = ctx.delete_item(client_id(&headers), &id, &item_id).await;
+    view_list(Path(id), State(ctx), headers).await
 }
 
 pub async fn toggle_item(
-    Path((id, idx)): Path<(String, usize)>,
+    Path((id, item_id)): Path<(String, String)>,
     State(ctx): State<AppContext>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        if let Some(item) = list.items.get_mut(idx) {
-            item.completed = !item.completed;
-        }
-    }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+    let _ = ctx.toggle_item(client_id(&headers), &id, &item_id).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn increment_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let _ = ctx
+        .step_quantity(client_id(&headers), &id, &item_id, true)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn decrement_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let _ = ctx
+        .step_quantity(client_id(&headers), &id, &item_id, false)
+        .await;
+    view_list(Path(id), State(ctx), headers).await
 }
 
 pub async fn toggle_show_completed(
@@ -100,12 +283,8 @@ pub async fn toggle_show_completed(
     State(ctx): State<AppContext>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        list.show_completed = !list.show_completed;
-    }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+    let _ = ctx.toggle_show_completed(client_id(&headers), &id).await;
+    view_list(Path(id), State(ctx), headers).await
 }
 
 pub async fn delete_completed_items(
@@ -113,26 +292,146 @@ pub async fn delete_completed_items(
     State(ctx): State<AppContext>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        list.items.retain(|item| !item.completed);
-    }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+    let _ = ctx.clear_completed(client_id(&headers), &id).await;
+    view_list(Path(id), State(ctx), headers).await
 }
 
 pub async fn sort_list(
     Path(id): Path<String>,
     State(ctx): State<AppContext>,
     headers: HeaderMap,
+    Query(params): Query<SortParams>,
+) -> impl IntoResponse {
+    let _ = ctx.sort_list(client_id(&headers), &id, params.by).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn merge_duplicates(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let _ = ctx.merge_duplicates(client_id(&headers), &id).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn move_item(
+    Path((id, item_id)): Path<(String, String)>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<MoveForm>,
+) -> impl IntoResponse {
+    let to = match (form.after, form.before) {
+        (Some(after), _) => Some(MoveTo::After(after)),
+        (None, Some(before)) => Some(MoveTo::Before(before)),
+        (None, None) => None,
+    };
+    if let Some(to) = to {
+        let _ = ctx.move_item(client_id(&headers), &id, &item_id, to).await;
+    }
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn undo(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let _ = ctx.undo(client_id(&headers), &id).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn redo(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let _ = ctx.redo(client_id(&headers), &id).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// Changes the display name only; the id, and with it the list's URL, stays.
+pub async fn rename_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<RenameListForm>,
+) -> impl IntoResponse {
+    let patch = ListPatch {
+        name: Some(form.name),
+        ..Default::default()
+    };
+    let _ = ctx.update_list(client_id(&headers), &id, patch).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+pub async fn save_template(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<SaveTemplateForm>,
 ) -> impl IntoResponse {
-    if let Some(list) = ctx.state.write().unwrap().get_mut(&id) {
-        list.items
-            .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let _ = ctx.save_template(&id, Some(&form.name)).await;
+    view_list(Path(id), State(ctx), headers).await
+}
+
+/// Shows the copy, and puts its URL in the address bar.
+pub async fn duplicate_list(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<DuplicateList>,
+) -> Response {
+    let copy = ctx
+        .duplicate_list(
+            client_id(&headers),
+            &id,
+            form.name.as_deref(),
+            form.reset_completed,
+        )
+        .await;
+    match copy {
+        Ok((copy_id, _)) => (
+            [("HX-Push-Url", format!("/list/{}", copy_id))],
+            render_list(&ctx, &copy_id, false),
+        )
+            .into_response(),
+        Err(_) => render_list(&ctx, &id, shopping(&headers)).into_response(),
     }
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
-    view_list(Path(id), State(ctx)).await
+}
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    at: Option<u64>,
+}
+
+/// The list's change log, or with `?at=` the list as it was at that moment.
+pub async fn history(
+    Path(id): Path<String>,
+    State(ctx): State<AppContext>,
+    Query(params): Query<HistoryParams>,
+) -> Html<String> {
+    let content = match params.at {
+        Some(at) => match ctx.list_at(&id, at).await {
+            Ok(list) => history_tpl::render_at(&id, list.as_ref(), at),
+            Err(err) => html! { (err) },
+        },
+        None => match ctx.changes_to(&id).await {
+            Ok(changes) => {
+                // A deleted list is only known by the name it was deleted
+                // under.
+                let name = ctx.list(&id).map(|list| list.name).unwrap_or_else(|_| {
+                    changes
+                        .iter()
+                        .find(|change| change.op == "delete_list")
+                        .map_or(id.clone(), |change| change.detail.clone())
+                });
+                history_tpl::render(&id, &name, &changes)
+            }
+            Err(err) => html! { (err) },
+        },
+    };
+    Html(layout::render(content).into_string())
 }
 
 pub async fn delete_list(
@@ -140,9 +439,7 @@ pub async fn delete_list(
     State(ctx): State<AppContext>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    ctx.state.write().unwrap().remove(&id);
-    save_data(&ctx.state).await;
-    broadcast_update(&ctx, &headers);
+    let _ = ctx.delete_list(client_id(&headers), &id).await;
 
     let lists = ctx.state.read().unwrap();
     let content = crate::templates::home::lists_view(&lists);
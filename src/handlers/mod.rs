@@ -1,3 +1,6 @@
+pub mod api;
 pub mod home;
 pub mod list;
 pub mod sse;
+pub mod stores;
+pub mod trash;
@@ -1,24 +1,50 @@
+use crate::events::{Message, Update};
 use crate::state::AppContext;
 use axum::{
-    extract::State,
+    extract::{Query, State},
+    http::HeaderMap,
     response::{Sse, sse::Event},
 };
-use futures::stream::{Stream, StreamExt};
-use std::{convert::Infallible, time::Duration};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::convert::Infallible;
 use tokio_stream::wrappers::BroadcastStream;
 
+#[derive(Deserialize)]
+pub struct SseParams {
+    last_event_id: Option<u64>,
+}
+
 pub async fn sse_handler(
     State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<SseParams>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = ctx.update_tx.subscribe();
-    let stream = BroadcastStream::new(rx).map(|result| match result {
-        Ok(data) => Ok(Event::default().data(data)),
-        Err(_) => Ok(Event::default().data("")),
+    // Browsers send the header when an EventSource reconnects on its own; the
+    // client script passes the query parameter when it reconnects by hand.
+    let last_id = headers
+        .get("Last-Event-ID")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or(params.last_event_id);
+
+    let (catchup, rx) = ctx.updates.subscribe(last_id);
+    let live = BroadcastStream::new(rx).map(|result| match result {
+        Ok(message) => to_event(message),
+        // This client fell further behind than the channel buffers.
+        Err(_) => Event::default().data(Update::Resync.encode("")),
     });
+    let stream = stream::iter(catchup).map(to_event).chain(live).map(Ok);
 
     Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
-            .interval(Duration::from_secs(30))
+            .interval(ctx.config.keep_alive)
             .text("keep-alive"),
     )
 }
+
+fn to_event(message: Message) -> Event {
+    Event::default()
+        .id(message.id.to_string())
+        .data(message.data)
+}
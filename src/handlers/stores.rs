@@ -0,0 +1,54 @@
+use crate::events::client_id;
+use crate::models::{NewStoreLayout, StoreLayoutForm, StoreLayoutPatch};
+use crate::state::AppContext;
+use crate::templates::{layout, stores as stores_tpl};
+use axum::{
+    Form,
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Html,
+};
+
+pub async fn view_stores(State(ctx): State<AppContext>) -> Html<String> {
+    let content = stores_tpl::render(&ctx.store_layouts(), &ctx.categories());
+    Html(layout::render(content).into_string())
+}
+
+pub async fn create(
+    State(ctx): State<AppContext>,
+    Form(form): Form<StoreLayoutForm>,
+) -> Html<String> {
+    let new = NewStoreLayout {
+        categories: form.categories(),
+        name: form.name,
+    };
+    let _ = ctx.create_store_layout(new).await;
+    view_stores(State(ctx)).await
+}
+
+pub async fn update(
+    Path(store_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Form(form): Form<StoreLayoutForm>,
+) -> Html<String> {
+    let patch = StoreLayoutPatch {
+        categories: Some(form.categories()),
+        name: Some(form.name),
+    };
+    let _ = ctx
+        .update_store_layout(client_id(&headers), &store_id, patch)
+        .await;
+    view_stores(State(ctx)).await
+}
+
+pub async fn delete(
+    Path(store_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Html<String> {
+    let _ = ctx
+        .delete_store_layout(client_id(&headers), &store_id)
+        .await;
+    view_stores(State(ctx)).await
+}
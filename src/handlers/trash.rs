@@ -0,0 +1,34 @@
+use crate::events::client_id;
+use crate::state::AppContext;
+use crate::templates::{layout, trash as trash_tpl};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Html,
+};
+
+pub async fn view_trash(State(ctx): State<AppContext>) -> Html<String> {
+    let trash = ctx.trash();
+    let lists = ctx.state.read().unwrap();
+    let content = trash_tpl::render(&trash, &lists, ctx.config.trash_retention);
+    Html(layout::render(content).into_string())
+}
+
+pub async fn restore(
+    Path(entry_id): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Html<String> {
+    let _ = ctx.restore(client_id(&headers), &entry_id).await;
+    view_trash(State(ctx)).await
+}
+
+pub async fn purge(Path(entry_id): Path<String>, State(ctx): State<AppContext>) -> Html<String> {
+    let _ = ctx.purge(&entry_id).await;
+    view_trash(State(ctx)).await
+}
+
+pub async fn empty(State(ctx): State<AppContext>) -> Html<String> {
+    ctx.empty_trash().await;
+    view_trash(State(ctx)).await
+}
@@ -0,0 +1,232 @@
+use crate::models::GroceryList;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How many changes per list can be undone.
+const DEPTH: usize = 50;
+
+/// Undo and redo stacks for every list, kept in memory.
+#[derive(Default)]
+pub struct History {
+    lists: HashMap<String, Stacks>,
+}
+
+#[derive(Default)]
+struct Stacks {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+}
+
+/// One change to a list: what it looked like right before and right after.
+///
+/// Undoing does not restore `before` wholesale. Only the difference between
+/// the two versions is reverted, so changes other people made to the list
+/// in the meantime are kept.
+#[derive(Clone)]
+pub struct Entry {
+    pub label: &'static str,
+    before: GroceryList,
+    after: GroceryList,
+    /// Another list changed in the same step, such as the one items were
+    /// moved to, with its own change. It is undone and redone together with
+    /// this one.
+    pub linked: Option<(String, Box<Entry>)>,
+}
+
+impl History {
+    /// Records a change to list `id` and forgets everything that could be
+    /// redone.
+    pub fn record(
+        &mut self,
+        id: &str,
+        label: &'static str,
+        before: GroceryList,
+        after: GroceryList,
+    ) {
+        self.push(
+            id,
+            Entry {
+                label,
+                before,
+                after,
+                linked: None,
+            },
+        );
+    }
+
+    /// Records a change to list `id` that also changed list `linked_id`, as
+    /// one step on the history of `id`.
+    pub fn record_linked(
+        &mut self,
+        id: &str,
+        label: &'static str,
+        (before, after): (GroceryList, GroceryList),
+        linked_id: &str,
+        (linked_before, linked_after): (GroceryList, GroceryList),
+    ) {
+        let linked = Entry {
+            label,
+            before: linked_before,
+            after: linked_after,
+            linked: None,
+        };
+        self.push(
+            id,
+            Entry {
+                label,
+                before,
+                after,
+                linked: Some((linked_id.to_string(), Box::new(linked))),
+            },
+        );
+    }
+
+    fn push(&mut self, id: &str, entry: Entry) {
+        let stacks = self.lists.entry(id.to_string()).or_default();
+        if stacks.undo.len() == DEPTH {
+            stacks.undo.pop_front();
+        }
+        stacks.undo.push_back(entry);
+        stacks.redo.clear();
+    }
+
+    /// Moves the latest change of list `id` to its redo stack and returns it.
+    pub fn undo(&mut self, id: &str) -> Option<Entry> {
+        let stacks = self.lists.get_mut(id)?;
+        let entry = stacks.undo.pop_back()?;
+        stacks.redo.push(entry.clone());
+        Some(entry)
+    }
+
+    /// Moves the latest undone change of list `id` back to its undo stack
+    /// and returns it.
+    pub fn redo(&mut self, id: &str) -> Option<Entry> {
+        let stacks = self.lists.get_mut(id)?;
+        let entry = stacks.redo.pop()?;
+        stacks.undo.push_back(entry.clone());
+        Some(entry)
+    }
+
+    pub fn forget(&mut self, id: &str) {
+        self.lists.remove(id);
+    }
+}
+
+impl Entry {
+    pub fn revert(&self, list: &mut GroceryList) {
+        apply(list, &self.after, &self.before);
+    }
+
+    pub fn reapply(&self, list: &mut GroceryList) {
+        apply(list, &self.before, &self.after);
+    }
+}
+
+/// Applies the change that turned `from` into `to` onto `list`, matching
+/// items by id.
+fn apply(list: &mut GroceryList, from: &GroceryList, to: &GroceryList) {
+    if from.name != to.name {
+        list.name = to.name.clone();
+    }
+    if from.show_completed != to.show_completed {
+        list.show_completed = to.show_completed;
+    }
+    if from.store != to.store {
+        list.store = to.store.clone();
+    }
+
+    let from_ids: HashSet<&str> = from.items.iter().map(|item| item.id.as_str()).collect();
+    let to_ids: HashSet<&str> = to.items.iter().map(|item| item.id.as_str()).collect();
+
+    list.items
+        .retain(|item| !from_ids.contains(item.id.as_str()) || to_ids.contains(item.id.as_str()));
+
+    for item in &to.items {
+        if let Some(old) = from.items.iter().find(|old| old.id == item.id)
+            && old != item
+            && let Some(current) = list.item_mut(&item.id)
+        {
+            *current = item.clone();
+        }
+    }
+
+    // Reordered items take the places they already occupy, in their new order.
+    let order = |list: &GroceryList| -> Vec<String> {
+        list.items
+            .iter()
+            .filter(|item| from_ids.contains(item.id.as_str()) && to_ids.contains(item.id.as_str()))
+            .map(|item| item.id.clone())
+            .collect()
+    };
+    if order(from) != order(to) {
+        let rank: HashMap<&str, usize> = to
+            .items
+            .iter()
+            .enumerate()
+            .map(|(rank, item)| (item.id.as_str(), rank))
+            .collect();
+        let slots: Vec<usize> = (0..list.items.len())
+            .filter(|&index| rank.contains_key(list.items[index].id.as_str()))
+            .collect();
+        let mut moved: Vec<_> = slots
+            .iter()
+            .map(|&index| list.items[index].clone())
+            .collect();
+        moved.sort_by_key(|item| rank[item.id.as_str()]);
+        for (index, item) in slots.into_iter().zip(moved) {
+            list.items[index] = item;
+        }
+    }
+
+    // Items that come back are put after the item they followed in `to`.
+    for (index, item) in to.items.iter().enumerate() {
+        if from_ids.contains(item.id.as_str()) || list.items.iter().any(|i| i.id == item.id) {
+            continue;
+        }
+        let position = to.items[..index]
+            .iter()
+            .rev()
+            .find_map(|prev| list.items.iter().position(|i| i.id == prev.id))
+            .map_or(0, |position| position + 1);
+        list.items.insert(position, item.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(store: Option<&str>) -> GroceryList {
+        GroceryList {
+            name: "Groceries".to_string(),
+            items: vec![],
+            show_completed: true,
+            store: store.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn undoes_and_redoes_a_store_change() {
+        let mut history = History::default();
+        history.record("g", "List changed", list(None), list(Some("corner")));
+
+        let mut current = list(Some("corner"));
+        history.undo("g").unwrap().revert(&mut current);
+        assert_eq!(current.store, None);
+        history.redo("g").unwrap().reapply(&mut current);
+        assert_eq!(current.store.as_deref(), Some("corner"));
+    }
+
+    #[test]
+    fn keeps_a_store_picked_in_the_meantime() {
+        let mut history = History::default();
+        let mut renamed = list(Some("corner"));
+        renamed.name = "Food".to_string();
+        history.record("g", "List changed", list(Some("corner")), renamed);
+
+        let mut current = list(Some("market"));
+        current.name = "Food".to_string();
+        history.undo("g").unwrap().revert(&mut current);
+        assert_eq!(current.name, "Groceries");
+        assert_eq!(current.store.as_deref(), Some("market"));
+    }
+}
@@ -1,7 +1,20 @@
 mod assets;
+mod bulk;
+mod categories;
+mod changelog;
+mod config;
+mod duplicates;
+mod events;
 mod handlers;
+mod history;
 mod models;
+mod ops;
+mod purchases;
+mod quantity;
+mod slug;
 mod state;
+mod store;
+mod suggestions;
 mod templates;
 
 use axum::{
@@ -9,14 +22,28 @@ use axum::{
     response::IntoResponse,
     routing::{get, post},
 };
-use handlers::{home, list, sse};
+use config::Config;
+use handlers::{api, home, list, sse, stores, trash};
 use state::AppContext;
 
-const BIND: &'static str = "0.0.0.0:3000";
-
 #[tokio::main]
 async fn main() {
-    let ctx = AppContext::new().await;
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let bind = config.bind.clone();
+
+    let ctx = match AppContext::new(config).await {
+        Ok(ctx) => ctx,
+        Err(err) => {
+            eprintln!("Failed to load lists: {}", err);
+            std::process::exit(1);
+        }
+    };
 
     let app = Router::new()
         .route("/", get(home::home))
@@ -24,9 +51,24 @@ async fn main() {
         .route("/create", post(home::create_list))
         .route("/list/:id", get(list::view_list))
         .route("/list/:id/add", post(list::add_item))
-        .route("/list/:id/toggle/:idx", post(list::toggle_item))
-        .route("/list/:id/edit/:idx", post(list::edit_item))
-        .route("/list/:id/delete-item/:idx", post(list::delete_item))
+        .route("/list/:id/add-items", post(list::add_items))
+        .route("/list/:id/preview", post(list::preview_items))
+        .route("/list/:id/suggestions", get(list::suggestions))
+        .route("/list/:id/buy-again", post(list::buy_again))
+        .route("/list/:id/toggle/:item", post(list::toggle_item))
+        .route("/list/:id/edit/:item", post(list::edit_item))
+        .route("/list/:id/increment/:item", post(list::increment_item))
+        .route("/list/:id/decrement/:item", post(list::decrement_item))
+        .route(
+            "/list/:id/details/:item",
+            get(list::item_details).post(list::save_item_details),
+        )
+        .route(
+            "/list/:id/move-to/:item",
+            get(list::transfer_picker).post(list::move_to),
+        )
+        .route("/list/:id/copy-to/:item", post(list::copy_to))
+        .route("/list/:id/delete-item/:item", post(list::delete_item))
         .route(
             "/list/:id/toggle-completed",
             post(list::toggle_show_completed),
@@ -35,14 +77,33 @@ async fn main() {
             "/list/:id/delete-completed",
             post(list::delete_completed_items),
         )
+        .route("/list/:id/move/:item", post(list::move_item))
         .route("/list/:id/sort", post(list::sort_list))
+        .route("/list/:id/merge-duplicates", post(list::merge_duplicates))
+        .route("/list/:id/rename", post(list::rename_list))
+        .route("/list/:id/save-template", post(list::save_template))
+        .route("/list/:id/duplicate", post(list::duplicate_list))
         .route("/list/:id/delete", post(list::delete_list))
+        .route("/list/:id/undo", post(list::undo))
+        .route("/list/:id/redo", post(list::redo))
+        .route("/list/:id/history", get(list::history))
+        .route("/list/:id/shopping", post(list::toggle_shopping))
+        .route("/list/:id/store", post(list::select_store))
+        .route("/templates/:template/delete", post(home::delete_template))
+        .route("/stores", get(stores::view_stores).post(stores::create))
+        .route("/stores/:store", post(stores::update))
+        .route("/stores/:store/delete", post(stores::delete))
+        .route("/trash", get(trash::view_trash))
+        .route("/trash/empty", post(trash::empty))
+        .route("/trash/:entry/restore", post(trash::restore))
+        .route("/trash/:entry/purge", post(trash::purge))
         .route("/events", get(sse::sse_handler))
+        .nest(api::PREFIX, api::routes())
         .route("/favicon.ico", get(favicon_handler))
         .with_state(ctx);
 
-    let listener = tokio::net::TcpListener::bind(BIND).await.unwrap();
-    println!("Server running on http://{}", BIND);
+    let listener = tokio::net::TcpListener::bind(&bind).await.unwrap();
+    println!("Server running on http://{}", bind);
     axum::serve(listener, app).await.unwrap();
 }
 
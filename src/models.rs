@@ -1,24 +1,403 @@
-use serde::{Deserialize, Serialize};
+use crate::quantity;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Item {
+    /// Stable identifier used in URLs. Older files have no ids; they are
+    /// assigned on load by `GroceryList::assign_missing_ids`.
+    #[serde(default)]
+    pub id: String,
     pub name: String,
     pub completed: bool,
+    /// How many or how much to buy; `None` when not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f64>,
+    /// Unit of `quantity`, e.g. `g` or `pack`; `None` for a plain count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Free text shown under the name, e.g. "the organic one".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// Section the item is listed under, e.g. "Dairy".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+impl Item {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: new_id(),
+            name,
+            completed: false,
+            quantity: None,
+            unit: None,
+            notes: None,
+            category: None,
+        }
+    }
+
+    /// The same item under a fresh id.
+    pub fn duplicate(&self) -> Self {
+        Self {
+            id: new_id(),
+            ..self.clone()
+        }
+    }
+
+    /// The quantity as shown on the item's badge, e.g. "2×" or "500 g".
+    pub fn amount(&self) -> Option<String> {
+        self.quantity
+            .map(|quantity| quantity::format(quantity, self.unit.as_deref()))
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct GroceryList {
     pub name: String,
     pub items: Vec<Item>,
     pub show_completed: bool,
+    /// Id of the `StoreLayout` the list is shopped at, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
 }
 
-#[derive(Deserialize)]
+impl GroceryList {
+    pub fn item(&self, id: &str) -> Option<&Item> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn item_mut(&mut self, id: &str) -> Option<&mut Item> {
+        self.items.iter_mut().find(|item| item.id == id)
+    }
+
+    /// Gives every item without an id a fresh one. Returns whether anything
+    /// changed, so callers know the list has to be written back.
+    pub fn assign_missing_ids(&mut self) -> bool {
+        let mut changed = false;
+        for item in self.items.iter_mut().filter(|item| item.id.is_empty()) {
+            item.id = new_id();
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Items kept to start new lists with, such as a "Camping trip" packing
+/// list or the weekly groceries.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct ListTemplate {
+    pub id: String,
+    pub name: String,
+    pub items: Vec<Item>,
+}
+
+impl ListTemplate {
+    pub fn new(name: String, items: Vec<Item>) -> Self {
+        Self {
+            id: new_id(),
+            name,
+            items,
+        }
+    }
+}
+
+/// A supermarket's walk path: categories in the order its aisles are
+/// passed on the way through.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoreLayout {
+    pub id: String,
+    pub name: String,
+    pub categories: Vec<String>,
+}
+
+impl StoreLayout {
+    pub fn new(name: String, categories: Vec<String>) -> Self {
+        Self {
+            id: new_id(),
+            name,
+            categories,
+        }
+    }
+
+    /// Where `category` comes on the walk, ignoring case. Categories the
+    /// store does not list come after every one it does.
+    pub fn position(&self, category: &str) -> usize {
+        self.categories
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(category))
+            .unwrap_or(self.categories.len())
+    }
+}
+
+/// Sort key for a section: along the walk path of `layout`, or just
+/// alphabetically without one. No category at all goes last.
+pub fn category_order(layout: Option<&StoreLayout>, category: Option<&str>) -> (usize, String) {
+    match category {
+        Some(category) => (
+            layout.map_or(0, |layout| layout.position(category)),
+            category.to_lowercase(),
+        ),
+        None => (usize::MAX, String::new()),
+    }
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// A deleted list or item, kept until it is restored or purged.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrashEntry {
+    pub id: String,
+    /// When it was deleted, in seconds since the Unix epoch.
+    pub deleted_at: u64,
+    /// Id of the deleted list, or of the list the item was deleted from.
+    pub list_id: String,
+    #[serde(flatten)]
+    pub content: Trashed,
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trashed {
+    List {
+        list: GroceryList,
+    },
+    /// `position` is where the item was in its list, so it can be put back
+    /// in the same place.
+    Item {
+        item: Item,
+        position: usize,
+    },
+}
+
+impl TrashEntry {
+    pub fn new(list_id: &str, content: Trashed) -> Self {
+        Self {
+            id: new_id(),
+            deleted_at: now(),
+            list_id: list_id.to_string(),
+            content,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.content {
+            Trashed::List { list } => &list.name,
+            Trashed::Item { item, .. } => &item.name,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct CreateForm {
     pub name: String,
+    /// Id of a template to start the list with the items of.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Uncheck every item taken from the template.
+    #[serde(default)]
+    pub reset_completed: bool,
+}
+
+/// A copy of an existing list.
+#[derive(Deserialize, ToSchema)]
+pub struct DuplicateList {
+    /// Defaults to the name of the list with "(copy)" after it.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Uncheck every item of the copy.
+    #[serde(default)]
+    pub reset_completed: bool,
+}
+
+/// A template saved from a list.
+#[derive(Deserialize, ToSchema)]
+pub struct NewTemplate {
+    /// Id of the list to take the items of.
+    pub list: String,
+    /// Defaults to the name of the list. Saving under the name of an
+    /// existing template replaces its items.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SaveTemplateForm {
+    pub name: String,
 }
 
 #[derive(Deserialize)]
+pub struct RenameListForm {
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct AddItemForm {
     pub item: String,
 }
+
+/// Everything the item details sheet edits at once. Empty fields clear the
+/// quantity, unit, notes or category.
+#[derive(Deserialize)]
+pub struct ItemDetailsForm {
+    pub name: String,
+    pub quantity: String,
+    pub unit: String,
+    pub notes: String,
+    pub category: String,
+}
+
+/// Where `move_item` puts an item: at an index of the list, or right
+/// before or after another item.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveTo {
+    /// Index in the list once the item has been taken out of it; larger
+    /// indexes move it to the end.
+    Position(usize),
+    /// Id of the item to put it in front of.
+    Before(String),
+    /// Id of the item to put it behind.
+    After(String),
+}
+
+/// Drop target of a dragged item: the item it landed behind, or the one it
+/// landed in front of when it went to the top.
+#[derive(Deserialize)]
+pub struct MoveForm {
+    pub after: Option<String>,
+    pub before: Option<String>,
+}
+
+/// Items to move or copy to another list.
+#[derive(Deserialize, ToSchema)]
+pub struct TransferItems {
+    /// Id of the list they go to.
+    pub to: String,
+    /// Ids of the items, in the order they are added to that list.
+    pub items: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TransferForm {
+    pub to: String,
+}
+
+/// How `sort_list` orders items.
+#[derive(Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    /// Alphabetically, ignoring case.
+    #[default]
+    Name,
+    /// By category, then by name; items without a category go last.
+    Category,
+    /// Along the walk path of the list's store, then by name. Lists without
+    /// a store are sorted by category.
+    Store,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestParams {
+    /// What has been typed so far; a quantity in front is ignored.
+    #[serde(default)]
+    pub q: String,
+}
+
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SortParams {
+    #[serde(default)]
+    pub by: SortBy,
+}
+
+/// Partial update of a list; fields left out stay unchanged.
+#[derive(Default, Deserialize, ToSchema)]
+pub struct ListPatch {
+    pub name: Option<String>,
+    pub show_completed: Option<bool>,
+    /// Id of a store layout; `null` means the list is not tied to a store.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub store: Option<Option<String>>,
+}
+
+/// A new store layout.
+#[derive(Deserialize, ToSchema)]
+pub struct NewStoreLayout {
+    pub name: String,
+    /// Categories in walk order.
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+/// Partial update of a store layout; fields left out stay unchanged.
+#[derive(Default, Deserialize, ToSchema)]
+pub struct StoreLayoutPatch {
+    pub name: Option<String>,
+    pub categories: Option<Vec<String>>,
+}
+
+/// A store layout as edited on the stores page, one category per line.
+#[derive(Deserialize)]
+pub struct StoreLayoutForm {
+    pub name: String,
+    pub categories: String,
+}
+
+impl StoreLayoutForm {
+    pub fn categories(&self) -> Vec<String> {
+        self.categories.lines().map(str::to_string).collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SelectStoreForm {
+    /// Empty for no store.
+    pub store: String,
+}
+
+/// Partial update of an item; fields left out stay unchanged.
+#[derive(Default, Deserialize, ToSchema)]
+pub struct ItemPatch {
+    pub name: Option<String>,
+    pub completed: Option<bool>,
+    /// `null` removes the quantity.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<f64>)]
+    pub quantity: Option<Option<f64>>,
+    /// `null` removes the unit.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub unit: Option<Option<String>>,
+    /// `null` or an empty string removes the notes.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub notes: Option<Option<String>>,
+    /// `null` or an empty string removes the category. The category is
+    /// remembered for items with the same name added later.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub category: Option<Option<String>>,
+}
+
+/// Tells a field sent as `null` (`Some(None)`) apart from one left out
+/// (`None`, through `#[serde(default)]`).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,1593 @@
+//! Every change to the lists goes through the operations in this module, so
+//! the HTML and JSON handlers share one path for updating memory, persisting
+//! and notifying clients.
+
+use crate::bulk;
+use crate::changelog::{self, Change};
+use crate::duplicates;
+use crate::events::{Action, Update};
+use crate::history::Entry;
+use crate::models::{
+    self, GroceryList, Item, ItemPatch, ListPatch, ListTemplate, MoveTo, NewStoreLayout, SortBy,
+    StoreLayout, StoreLayoutPatch, TrashEntry, Trashed,
+};
+use crate::purchases::Purchase;
+use crate::quantity;
+use crate::slug;
+use crate::state::AppContext;
+use crate::store::Lists;
+use crate::suggestions::NameIndex;
+use std::{fmt, io, time::Duration};
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    ListNotFound,
+    ItemNotFound,
+    EmptyName,
+    InvalidQuantity,
+    NothingToUndo,
+    NothingToRedo,
+    EntryNotFound,
+    StoreNotFound,
+    TemplateNotFound,
+    Storage(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ListNotFound => write!(f, "list not found"),
+            Error::ItemNotFound => write!(f, "item not found"),
+            Error::EmptyName => write!(f, "name must not be empty"),
+            Error::InvalidQuantity => write!(f, "quantity must be a positive number"),
+            Error::NothingToUndo => write!(f, "nothing to undo"),
+            Error::NothingToRedo => write!(f, "nothing to redo"),
+            Error::EntryNotFound => write!(f, "trash entry not found"),
+            Error::StoreNotFound => write!(f, "store not found"),
+            Error::TemplateNotFound => write!(f, "template not found"),
+            Error::Storage(err) => write!(f, "storage error: {}", err),
+        }
+    }
+}
+
+fn non_empty(name: &str) -> Result<String, Error> {
+    match name.trim() {
+        "" => Err(Error::EmptyName),
+        name => Ok(name.to_string()),
+    }
+}
+
+fn positive(quantity: Option<f64>) -> Result<Option<f64>, Error> {
+    match quantity {
+        Some(q) if !(q.is_finite() && q > 0.0) => Err(Error::InvalidQuantity),
+        quantity => Ok(quantity),
+    }
+}
+
+/// Trims optional text; an empty string counts as no text at all.
+fn optional(text: Option<String>) -> Option<String> {
+    text.map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// Copies of `items` under fresh ids, unchecked if `reset_completed` is set.
+fn copy_items(items: &[Item], reset_completed: bool) -> Vec<Item> {
+    items
+        .iter()
+        .map(|item| Item {
+            completed: item.completed && !reset_completed,
+            ..item.duplicate()
+        })
+        .collect()
+}
+
+/// Trims categories, dropping empty ones and repeats.
+fn walk_path(categories: Vec<String>) -> Vec<String> {
+    let mut path: Vec<String> = vec![];
+    for category in categories {
+        let category = category.trim();
+        if !category.is_empty() && !path.iter().any(|c| c.eq_ignore_ascii_case(category)) {
+            path.push(category.to_string());
+        }
+    }
+    path
+}
+
+fn storage(err: io::Error) -> Error {
+    Error::Storage(err.to_string())
+}
+
+/// Merges `item` into the first item of `list` that is the same thing still
+/// to buy, and returns that item as it is now.
+fn merge_into(list: &mut GroceryList, item: &Item) -> Option<Item> {
+    list.items
+        .iter_mut()
+        .find_map(|existing| duplicates::merge(existing, item).then(|| existing.clone()))
+}
+
+fn item_ids(items: &[Item]) -> Vec<String> {
+    items.iter().map(|item| item.id.clone()).collect()
+}
+
+impl AppContext {
+    /// Runs `f` on list `id` while holding the write lock. If `f` changed the
+    /// list, the change is recorded in its history under `label`.
+    fn with_list<T>(
+        &self,
+        id: &str,
+        label: &'static str,
+        f: impl FnOnce(&mut GroceryList) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut lists = self.state.write().unwrap();
+        let list = lists.get_mut(id).ok_or(Error::ListNotFound)?;
+        let before = list.clone();
+        let result = f(list)?;
+        if *list != before {
+            self.history
+                .lock()
+                .unwrap()
+                .record(id, label, before, list.clone());
+        }
+        Ok(result)
+    }
+
+    /// Runs `f` on lists `id` and `other` under one write lock and records
+    /// what it did to both as a single change of `id`, so undoing it there
+    /// reverts `other` as well.
+    fn with_two_lists<T>(
+        &self,
+        id: &str,
+        other: &str,
+        label: &'static str,
+        f: impl FnOnce(&mut GroceryList, &mut GroceryList) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut lists = self.state.write().unwrap();
+        let before = lists.get(id).cloned().ok_or(Error::ListNotFound)?;
+        let other_before = lists.get(other).cloned().ok_or(Error::ListNotFound)?;
+        let (mut list, mut other_list) = (before.clone(), other_before.clone());
+        let result = f(&mut list, &mut other_list)?;
+        if list != before || other_list != other_before {
+            self.history.lock().unwrap().record_linked(
+                id,
+                label,
+                (before, list.clone()),
+                other,
+                (other_before, other_list.clone()),
+            );
+            lists.insert(id.to_string(), list);
+            lists.insert(other.to_string(), other_list);
+        }
+        Ok(result)
+    }
+
+    /// Runs `f` on item `item_id` of list `id` while holding the write lock.
+    fn with_item<T>(
+        &self,
+        id: &str,
+        item_id: &str,
+        label: &'static str,
+        f: impl FnOnce(&mut Item) -> T,
+    ) -> Result<T, Error> {
+        self.with_list(id, label, |list| {
+            list.item_mut(item_id).map(f).ok_or(Error::ItemNotFound)
+        })
+    }
+
+    pub fn list(&self, id: &str) -> Result<GroceryList, Error> {
+        self.state
+            .read()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or(Error::ListNotFound)
+    }
+
+    /// Creates an empty list under a fresh slug derived from `name`.
+    pub async fn create_list(
+        &self,
+        client_id: &str,
+        name: &str,
+    ) -> Result<(String, GroceryList), Error> {
+        let list = GroceryList {
+            name: non_empty(name)?,
+            items: vec![],
+            show_completed: true,
+            store: None,
+        };
+        let name = list.name.clone();
+        self.insert_list(client_id, list, "create_list", &name)
+            .await
+    }
+
+    /// Creates a list holding the items of template `template_id`, unchecked
+    /// if `reset_completed` is set.
+    pub async fn create_from_template(
+        &self,
+        client_id: &str,
+        name: &str,
+        template_id: &str,
+        reset_completed: bool,
+    ) -> Result<(String, GroceryList), Error> {
+        let name = non_empty(name)?;
+        let template = self.template(template_id)?;
+        let list = GroceryList {
+            name,
+            items: copy_items(&template.items, reset_completed),
+            show_completed: true,
+            store: None,
+        };
+        self.insert_list(client_id, list, "create_from_template", &template.name)
+            .await
+    }
+
+    /// Creates a copy of list `id`, named `name` or after the list with
+    /// "(copy)" added. The copy is shopped at the same store.
+    pub async fn duplicate_list(
+        &self,
+        client_id: &str,
+        id: &str,
+        name: Option<&str>,
+        reset_completed: bool,
+    ) -> Result<(String, GroceryList), Error> {
+        let name = name.map(non_empty).transpose()?;
+        let original = self.list(id)?;
+        let list = GroceryList {
+            name: name.unwrap_or_else(|| format!("{} (copy)", original.name)),
+            items: copy_items(&original.items, reset_completed),
+            ..original.clone()
+        };
+        self.insert_list(client_id, list, "duplicate_list", &original.name)
+            .await
+    }
+
+    /// Adds `list` under a fresh slug derived from its name.
+    async fn insert_list(
+        &self,
+        client_id: &str,
+        list: GroceryList,
+        op: &str,
+        detail: &str,
+    ) -> Result<(String, GroceryList), Error> {
+        let id = {
+            let mut lists = self.state.write().unwrap();
+            let id = slug::unique_slug(&list.name, |id| lists.contains_key(id));
+            lists.insert(id.clone(), list.clone());
+            id
+        };
+        self.persist(client_id, &id, op, detail).await;
+        self.broadcast(client_id, Update::list_created(&id, &list));
+        Ok((id, list))
+    }
+
+    /// Renames a list, changes whether it shows completed items and/or picks
+    /// the store it is shopped at. The id, and with it the list's URL, never
+    /// changes.
+    pub async fn update_list(
+        &self,
+        client_id: &str,
+        id: &str,
+        patch: ListPatch,
+    ) -> Result<GroceryList, Error> {
+        let name = patch.name.as_deref().map(non_empty).transpose()?;
+        if let Some(Some(store)) = &patch.store {
+            self.store_layout(store)?;
+        }
+        let (list, renamed, layout_changed) = self.with_list(id, "List changed", |list| {
+            let renamed = name.as_ref().is_some_and(|name| *name != list.name);
+            if let Some(name) = name {
+                list.name = name;
+            }
+            let visibility_changed = patch
+                .show_completed
+                .is_some_and(|show| show != list.show_completed);
+            if let Some(show) = patch.show_completed {
+                list.show_completed = show;
+            }
+            let store_changed = patch
+                .store
+                .as_ref()
+                .is_some_and(|store| *store != list.store);
+            if let Some(store) = patch.store {
+                list.store = store;
+            }
+            Ok((list.clone(), renamed, visibility_changed || store_changed))
+        })?;
+
+        let detail = if renamed { list.name.as_str() } else { "" };
+        self.persist(client_id, id, "update_list", detail).await;
+        if renamed {
+            self.broadcast(
+                client_id,
+                Update::ListRenamed {
+                    list_id: id.to_string(),
+                    name: list.name.clone(),
+                },
+            );
+        }
+        if layout_changed {
+            self.broadcast(
+                client_id,
+                Update::Reload {
+                    list_id: Some(id.to_string()),
+                },
+            );
+        }
+        Ok(list)
+    }
+
+    pub async fn toggle_show_completed(&self, client_id: &str, id: &str) -> Result<bool, Error> {
+        let show = self.with_list(id, "Completed items shown or hidden", |list| {
+            list.show_completed = !list.show_completed;
+            Ok(list.show_completed)
+        })?;
+        let detail = if show {
+            "show completed items"
+        } else {
+            "hide completed items"
+        };
+        self.persist(client_id, id, "toggle_show_completed", detail)
+            .await;
+        self.broadcast(
+            client_id,
+            Update::Reload {
+                list_id: Some(id.to_string()),
+            },
+        );
+        Ok(show)
+    }
+
+    pub async fn delete_list(&self, client_id: &str, id: &str) -> Result<GroceryList, Error> {
+        let list = self
+            .state
+            .write()
+            .unwrap()
+            .remove(id)
+            .ok_or(Error::ListNotFound)?;
+        self.history.lock().unwrap().forget(id);
+        self.trash
+            .lock()
+            .unwrap()
+            .push(TrashEntry::new(id, Trashed::List { list: list.clone() }));
+        self.persist(client_id, id, "delete_list", &list.name).await;
+        self.persist_trash().await;
+        self.broadcast(
+            client_id,
+            Update::ListDeleted {
+                list_id: id.to_string(),
+            },
+        );
+        Ok(list)
+    }
+
+    /// Adds an item to the end of a list. A quantity typed with the name, as
+    /// in "2x milk" or "500 g flour", is split off into its own fields, and
+    /// the item is filed under the category its name was last given.
+    /// Returns the item as it is on the list, and whether it was merged into
+    /// the same item the list already had to buy.
+    pub async fn add_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        input: &str,
+    ) -> Result<(Item, bool), Error> {
+        let item = self.new_item(&non_empty(input)?);
+        self.push_item(client_id, id, item).await
+    }
+
+    fn new_item(&self, input: &str) -> Item {
+        let parsed = quantity::parse(input);
+        Item {
+            quantity: parsed.quantity,
+            unit: parsed.unit,
+            category: self.categories.lock().unwrap().get(&parsed.name),
+            ..Item::new(parsed.name)
+        }
+    }
+
+    /// The items `input` adds to a list: one per line, or per comma on a
+    /// single line, each parsed like the input of `add_item`.
+    pub fn parse_items(&self, input: &str) -> Vec<Item> {
+        bulk::split(input)
+            .iter()
+            .map(|entry| self.new_item(entry))
+            .collect()
+    }
+
+    /// Adds every item of pasted or typed text to the end of a list at once,
+    /// as one change that a single undo takes back.
+    pub async fn add_items(
+        &self,
+        client_id: &str,
+        id: &str,
+        input: &str,
+    ) -> Result<Vec<Item>, Error> {
+        let mut items = self.parse_items(input);
+        match items.len() {
+            0 => return Err(Error::EmptyName),
+            1 => {
+                let (item, _) = self.push_item(client_id, id, items.remove(0)).await?;
+                return Ok(vec![item]);
+            }
+            _ => {}
+        }
+
+        let (added, merged, item_count) = self.with_list(id, "Items added", |list| {
+            let mut added = vec![];
+            let mut merged: Vec<Item> = vec![];
+            for item in &items {
+                match merge_into(list, item) {
+                    Some(existing) => {
+                        merged.retain(|other| other.id != existing.id);
+                        merged.push(existing);
+                    }
+                    None => {
+                        list.items.push(item.clone());
+                        added.push(item.clone());
+                    }
+                }
+            }
+            // An item added by this same paste may have been merged into
+            // afterwards; it is new to everyone else all the same.
+            for item in added.iter_mut() {
+                if let Some(index) = merged.iter().position(|other| other.id == item.id) {
+                    *item = merged.remove(index);
+                }
+            }
+            Ok((added, merged, list.items.len()))
+        })?;
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        self.persist(client_id, id, "bulk_add", &names.join(", "))
+            .await;
+        if !added.is_empty() {
+            self.broadcast(client_id, Update::items_added(id, &added, item_count));
+        }
+        for item in &merged {
+            self.broadcast(client_id, Update::item_changed(id, item.clone()));
+        }
+        let message = match merged.len() {
+            0 => format!("{} items added", items.len()),
+            n => format!("{} items added, {} merged with the list", added.len(), n),
+        };
+        self.broadcast(client_id, Update::toast(id, &message, Action::Undo));
+        self.remember_names(id, &items).await;
+        Ok(added.into_iter().chain(merged).collect())
+    }
+
+    /// Adds `item` to the end of list `id`, unless the list already has it
+    /// to buy; then it is merged into that one. Returns the item as it is on
+    /// the list and whether it was merged.
+    async fn push_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        item: Item,
+    ) -> Result<(Item, bool), Error> {
+        let (added, merged, item_count) = self.with_list(id, "Item added", |list| {
+            if let Some(existing) = merge_into(list, &item) {
+                return Ok((existing, true, list.items.len()));
+            }
+            list.items.push(item.clone());
+            Ok((item.clone(), false, list.items.len()))
+        })?;
+        self.remember_names(id, std::slice::from_ref(&item)).await;
+
+        if merged {
+            let detail = match added.amount() {
+                Some(amount) => format!("{} {}", amount, added.name),
+                None => added.name.clone(),
+            };
+            self.persist(client_id, id, "merge_item", &detail).await;
+            self.broadcast(client_id, Update::item_changed(id, added.clone()));
+            let message = format!("“{}” was already on the list", added.name);
+            self.broadcast(client_id, Update::toast(id, &message, Action::Undo));
+        } else {
+            self.persist(client_id, id, "add_item", &added.name).await;
+            self.broadcast(client_id, Update::item_added(id, added.clone(), item_count));
+        }
+        Ok((added, merged))
+    }
+
+    /// Folds items still to buy that are the same thing into the first of
+    /// them, adding up their amounts. Returns the list as it is afterwards.
+    pub async fn merge_duplicates(&self, client_id: &str, id: &str) -> Result<GroceryList, Error> {
+        let (list, merged) = self.with_list(id, "Duplicates merged", |list| {
+            let mut kept: Vec<Item> = vec![];
+            let mut merged = vec![];
+            for item in list.items.drain(..) {
+                if kept.iter_mut().any(|other| duplicates::merge(other, &item)) {
+                    merged.push(item.name);
+                } else {
+                    kept.push(item);
+                }
+            }
+            list.items = kept;
+            Ok((list.clone(), merged))
+        })?;
+        if merged.is_empty() {
+            return Ok(list);
+        }
+        self.persist(client_id, id, "merge_duplicates", &merged.join(", "))
+            .await;
+        self.broadcast(
+            client_id,
+            Update::Reload {
+                list_id: Some(id.to_string()),
+            },
+        );
+        let message = match merged.len() {
+            1 => "1 duplicate merged".to_string(),
+            n => format!("{} duplicates merged", n),
+        };
+        self.broadcast(client_id, Update::toast(id, &message, Action::Undo));
+        Ok(list)
+    }
+
+    async fn remember_names(&self, id: &str, items: &[Item]) {
+        let at = changelog::now_ms();
+        let mut changed = NameIndex::default();
+        {
+            let mut names = self.names.lock().unwrap();
+            for item in items {
+                let (key, stats) = names.record(id, &item.name, at);
+                changed.insert(key, stats);
+            }
+        }
+        self.persist_names(&changed).await;
+    }
+
+    /// Notes that `items` were bought from list `id`.
+    async fn remember_purchases(&self, id: &str, items: &[Item]) {
+        if items.is_empty() {
+            return;
+        }
+        let at = changelog::now_ms();
+        {
+            let mut purchases = self.purchases.lock().unwrap();
+            for item in items.iter().rev() {
+                purchases.record(id, item, at);
+            }
+        }
+        self.persist_purchases().await;
+    }
+
+    /// What was bought from list `id`, most recent first.
+    pub fn purchases(&self, id: &str) -> Result<Vec<Purchase>, Error> {
+        self.list(id)?;
+        Ok(self.purchases.lock().unwrap().get(id).to_vec())
+    }
+
+    /// Puts an item bought from list `id` before back on it, with the
+    /// quantity, unit and category it had.
+    pub async fn buy_again(
+        &self,
+        client_id: &str,
+        id: &str,
+        name: &str,
+    ) -> Result<(Item, bool), Error> {
+        self.list(id)?;
+        let item = self
+            .purchases
+            .lock()
+            .unwrap()
+            .find(id, name)
+            .map(Purchase::to_item)
+            .ok_or(Error::ItemNotFound)?;
+        self.push_item(client_id, id, item).await
+    }
+
+    /// Item names added before that match what is typed in the add field of
+    /// list `id`, best first. A quantity typed in front is ignored, and
+    /// names the list already has unchecked are left out.
+    pub fn suggestions(&self, id: &str, input: &str) -> Result<Vec<String>, Error> {
+        let list = self.list(id)?;
+        let skip: Vec<&str> = list
+            .items
+            .iter()
+            .filter(|item| !item.completed)
+            .map(|item| item.name.as_str())
+            .collect();
+        let name = quantity::parse(input).name;
+        Ok(self
+            .names
+            .lock()
+            .unwrap()
+            .suggest(id, &name, &skip, changelog::now_ms()))
+    }
+
+    /// Renames an item, sets its completion state and/or changes its
+    /// quantity, unit, notes and category.
+    pub async fn update_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        item_id: &str,
+        patch: ItemPatch,
+    ) -> Result<Item, Error> {
+        let name = patch.name.as_deref().map(non_empty).transpose()?;
+        let quantity = patch.quantity.map(positive).transpose()?;
+        let recategorized = patch.category.is_some();
+        let (item, renamed, changed, toggled) =
+            self.with_item(id, item_id, "Item changed", |item| {
+                let renamed = name.as_ref().is_some_and(|name| *name != item.name);
+                if let Some(name) = name {
+                    item.name = name;
+                }
+                let details = (item.amount(), item.notes.clone(), item.category.clone());
+                if let Some(quantity) = quantity {
+                    item.quantity = quantity;
+                }
+                if let Some(unit) = patch.unit {
+                    item.unit = optional(unit);
+                }
+                if let Some(notes) = patch.notes {
+                    item.notes = optional(notes);
+                }
+                if let Some(category) = patch.category {
+                    item.category = optional(category);
+                }
+                let changed = (item.amount(), item.notes.clone(), item.category.clone()) != details;
+                let toggled = patch
+                    .completed
+                    .is_some_and(|completed| completed != item.completed);
+                if let Some(completed) = patch.completed {
+                    item.completed = completed;
+                }
+                (item.clone(), renamed, changed, toggled)
+            })?;
+
+        self.persist(client_id, id, "update_item", &item.name).await;
+        if recategorized {
+            self.remember_category(&item).await;
+        }
+        if renamed {
+            self.broadcast(client_id, Update::item_renamed(id, item.clone()));
+        } else if changed {
+            self.broadcast(client_id, Update::item_changed(id, item.clone()));
+        }
+        if toggled {
+            self.broadcast(client_id, Update::item_toggled(id, item.clone()));
+            if item.completed {
+                self.remember_purchases(id, std::slice::from_ref(&item))
+                    .await;
+            }
+        }
+        Ok(item)
+    }
+
+    /// Raises or lowers an item's quantity by one step of its unit. An item
+    /// without a quantity counts as one; lowering a plain count to one
+    /// removes it again, and nothing goes below one step.
+    pub async fn step_quantity(
+        &self,
+        client_id: &str,
+        id: &str,
+        item_id: &str,
+        up: bool,
+    ) -> Result<Item, Error> {
+        let (item, changed) = self.with_item(id, item_id, "Quantity changed", |item| {
+            let before = item.quantity;
+            let step = quantity::step(item.unit.as_deref());
+            let current = item.quantity.unwrap_or(1.0);
+            let next = if up { current + step } else { current - step };
+            if next >= step {
+                item.quantity = Some(next);
+            }
+            if item.unit.is_none() && item.quantity == Some(1.0) {
+                item.quantity = None;
+            }
+            (item.clone(), item.quantity != before)
+        })?;
+        // Already at the smallest amount: nothing to save or tell anyone.
+        if !changed {
+            return Ok(item);
+        }
+        let detail = match item.amount() {
+            Some(amount) => format!("{} {}", amount, item.name),
+            None => item.name.clone(),
+        };
+        self.persist(client_id, id, "change_quantity", &detail)
+            .await;
+        self.broadcast(client_id, Update::item_changed(id, item.clone()));
+        Ok(item)
+    }
+
+    pub async fn toggle_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        item_id: &str,
+    ) -> Result<Item, Error> {
+        let item = self.with_item(id, item_id, "Item checked off", |item| {
+            item.completed = !item.completed;
+            item.clone()
+        })?;
+        let op = if item.completed {
+            "check_item"
+        } else {
+            "uncheck_item"
+        };
+        self.persist(client_id, id, op, &item.name).await;
+        self.broadcast(client_id, Update::item_toggled(id, item.clone()));
+        if item.completed {
+            self.remember_purchases(id, std::slice::from_ref(&item))
+                .await;
+        }
+        Ok(item)
+    }
+
+    pub async fn delete_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        item_id: &str,
+    ) -> Result<Item, Error> {
+        let mut index = 0;
+        let (item, item_count) = self.with_list(id, "Item deleted", |list| {
+            index = list
+                .items
+                .iter()
+                .position(|item| item.id == item_id)
+                .ok_or(Error::ItemNotFound)?;
+            let item = list.items.remove(index);
+            Ok((item, list.items.len()))
+        })?;
+        self.discard(id, vec![(index, item.clone())]).await;
+        if item.completed {
+            self.remember_purchases(id, std::slice::from_ref(&item))
+                .await;
+        }
+        self.persist(client_id, id, "delete_item", &item.name).await;
+        self.broadcast(
+            client_id,
+            Update::ItemRemoved {
+                list_id: id.to_string(),
+                item_id: item.id.clone(),
+                item_count,
+            },
+        );
+        self.broadcast(client_id, Update::toast(id, "Item deleted", Action::Undo));
+        Ok(item)
+    }
+
+    /// Removes every completed item and returns them.
+    pub async fn clear_completed(&self, client_id: &str, id: &str) -> Result<Vec<Item>, Error> {
+        let (removed, item_count) = self.with_list(id, "Completed items deleted", |list| {
+            let (removed, remaining): (Vec<_>, Vec<_>) = list
+                .items
+                .drain(..)
+                .enumerate()
+                .partition(|(_, item)| item.completed);
+            list.items = remaining.into_iter().map(|(_, item)| item).collect();
+            Ok((removed, list.items.len()))
+        })?;
+        if removed.is_empty() {
+            return Ok(vec![]);
+        }
+        self.discard(id, removed.clone()).await;
+        let removed: Vec<Item> = removed.into_iter().map(|(_, item)| item).collect();
+        self.remember_purchases(id, &removed).await;
+        let names: Vec<&str> = removed.iter().map(|item| item.name.as_str()).collect();
+        self.persist(client_id, id, "clear_completed", &names.join(", "))
+            .await;
+        self.broadcast(
+            client_id,
+            Update::ItemsRemoved {
+                list_id: id.to_string(),
+                item_ids: item_ids(&removed),
+                item_count,
+            },
+        );
+        self.broadcast(
+            client_id,
+            Update::toast(id, "Completed items deleted", Action::Undo),
+        );
+        Ok(removed)
+    }
+
+    /// Sorts the items of a list alphabetically, by category or along the
+    /// walk path of its store, ignoring case each way.
+    pub async fn sort_list(
+        &self,
+        client_id: &str,
+        id: &str,
+        by: SortBy,
+    ) -> Result<GroceryList, Error> {
+        let layout = self
+            .list(id)?
+            .store
+            .and_then(|store| self.store_layout(&store).ok());
+        let list = self.with_list(id, "List sorted", |list| {
+            match by {
+                SortBy::Name => list.items.sort_by_key(|item| item.name.to_lowercase()),
+                SortBy::Category | SortBy::Store => {
+                    let layout = match by {
+                        SortBy::Store => layout.as_ref(),
+                        _ => None,
+                    };
+                    list.items.sort_by_key(|item| {
+                        (
+                            models::category_order(layout, item.category.as_deref()),
+                            item.name.to_lowercase(),
+                        )
+                    });
+                }
+            }
+            Ok(list.clone())
+        })?;
+        let detail = match by {
+            SortBy::Name => "",
+            SortBy::Category => "by category",
+            SortBy::Store => "by store",
+        };
+        self.persist(client_id, id, "sort_list", detail).await;
+        self.broadcast(
+            client_id,
+            Update::ItemsSorted {
+                list_id: id.to_string(),
+                item_ids: item_ids(&list.items),
+            },
+        );
+        Ok(list)
+    }
+
+    /// Moves an item to another place in its list.
+    pub async fn move_item(
+        &self,
+        client_id: &str,
+        id: &str,
+        item_id: &str,
+        to: MoveTo,
+    ) -> Result<GroceryList, Error> {
+        let (list, name) = self.with_list(id, "Item moved", |list| {
+            let index_of = |other: &str| {
+                list.items
+                    .iter()
+                    .position(|item| item.id == other)
+                    .ok_or(Error::ItemNotFound)
+            };
+            let from = index_of(item_id)?;
+            // Indexes of other items shift down by one once it is taken out.
+            let without = |index: usize| if index > from { index - 1 } else { index };
+            let to = match &to {
+                MoveTo::Position(position) => *position,
+                MoveTo::Before(other) => without(index_of(other)?),
+                MoveTo::After(other) => without(index_of(other)?) + 1,
+            };
+
+            let item = list.items.remove(from);
+            let name = item.name.clone();
+            list.items.insert(to.min(list.items.len()), item);
+            Ok((list.clone(), name))
+        })?;
+        self.persist(client_id, id, "move_item", &name).await;
+        self.broadcast(
+            client_id,
+            Update::ItemsSorted {
+                list_id: id.to_string(),
+                item_ids: item_ids(&list.items),
+            },
+        );
+        Ok(list)
+    }
+
+    /// Adds items of list `from` to the end of list `to`, and takes them off
+    /// `from` unless `copy` is set. The items get ids of their own in `to`,
+    /// so undoing the move on `from` cannot leave one id in both lists.
+    /// Returns the items as they are in `to`.
+    pub async fn transfer_items(
+        &self,
+        client_id: &str,
+        from: &str,
+        to: &str,
+        item_ids: &[String],
+        copy: bool,
+    ) -> Result<Vec<Item>, Error> {
+        let mut ids: Vec<&String> = vec![];
+        for item_id in item_ids {
+            if !ids.contains(&item_id) {
+                ids.push(item_id);
+            }
+        }
+        let source = self.list(from)?;
+        let items = ids
+            .iter()
+            .map(|item_id| {
+                source
+                    .item(item_id)
+                    .map(Item::duplicate)
+                    .ok_or(Error::ItemNotFound)
+            })
+            .collect::<Result<Vec<Item>, Error>>()?;
+        self.list(to)?;
+        if items.is_empty() || (!copy && from == to) {
+            return Ok(items);
+        }
+        let names = items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if copy {
+            let item_count = self.with_list(to, "Items copied in", |list| {
+                list.items.extend(items.iter().cloned());
+                Ok(list.items.len())
+            })?;
+            self.persist(client_id, to, "add_items", &names).await;
+            self.broadcast_added(client_id, to, &items, item_count);
+            return Ok(items);
+        }
+
+        let (from_count, to_count) =
+            self.with_two_lists(from, to, "Items moved", |source, target| {
+                source.items.retain(|item| !ids.contains(&&item.id));
+                target.items.extend(items.iter().cloned());
+                Ok((source.items.len(), target.items.len()))
+            })?;
+        self.persist(client_id, to, "add_items", &names).await;
+        self.persist(client_id, from, "move_items", &names).await;
+        self.broadcast_added(client_id, to, &items, to_count);
+        self.broadcast(
+            client_id,
+            Update::ItemsRemoved {
+                list_id: from.to_string(),
+                item_ids: ids.iter().map(|id| id.to_string()).collect(),
+                item_count: from_count,
+            },
+        );
+        Ok(items)
+    }
+
+    /// Tells everyone `items` were added to the end of list `id`, which now
+    /// holds `item_count` items.
+    fn broadcast_added(&self, client_id: &str, id: &str, items: &[Item], item_count: usize) {
+        for (offset, item) in items.iter().enumerate() {
+            let count = item_count - items.len() + offset + 1;
+            self.broadcast(client_id, Update::item_added(id, item.clone(), count));
+        }
+    }
+
+    /// Reverts the latest change to a list that has not been undone yet.
+    pub async fn undo(&self, client_id: &str, id: &str) -> Result<GroceryList, Error> {
+        self.travel(client_id, id, Action::Undo).await
+    }
+
+    /// Applies the latest undone change to a list again.
+    pub async fn redo(&self, client_id: &str, id: &str) -> Result<GroceryList, Error> {
+        self.travel(client_id, id, Action::Redo).await
+    }
+
+    async fn travel(
+        &self,
+        client_id: &str,
+        id: &str,
+        action: Action,
+    ) -> Result<GroceryList, Error> {
+        let (list, label, linked) = {
+            let mut lists = self.state.write().unwrap();
+            let mut history = self.history.lock().unwrap();
+            if !lists.contains_key(id) {
+                return Err(Error::ListNotFound);
+            }
+            let entry = match action {
+                Action::Undo => history.undo(id).ok_or(Error::NothingToUndo)?,
+                Action::Redo => history.redo(id).ok_or(Error::NothingToRedo)?,
+            };
+            let linked_step = entry.linked.is_some();
+            let mut trash = self.trash.lock().unwrap();
+            let mut travel = |id: &str, entry: &Entry| -> Option<GroceryList> {
+                let list = lists.get_mut(id)?;
+                let before = list.clone();
+                match action {
+                    Action::Undo => entry.revert(list),
+                    Action::Redo => entry.reapply(list),
+                }
+                // The store the list was shopped at may have been deleted since.
+                if let Some(store) = &list.store
+                    && self.store_layout(store).is_err()
+                {
+                    list.store = None;
+                }
+                // Items a step across two lists takes off one of them went
+                // to the other, not to the trash.
+                if !linked_step {
+                    sync_trash(&mut trash, id, &before, list);
+                }
+                Some(list.clone())
+            };
+            let list = travel(id, &entry).unwrap();
+            // A list changed along with this one may have been deleted since.
+            let linked = entry
+                .linked
+                .as_ref()
+                .filter(|(other, linked)| travel(other, linked).is_some())
+                .map(|(other, _)| other.clone());
+            (list, entry.label, linked)
+        };
+        self.persist_trash().await;
+
+        let op = match action {
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+        };
+        for id in std::iter::once(id).chain(linked.as_deref()) {
+            self.persist(client_id, id, op, label).await;
+            self.broadcast(
+                client_id,
+                Update::Reload {
+                    list_id: Some(id.to_string()),
+                },
+            );
+        }
+        // Whoever undid something gets the chance to redo it, and the other
+        // way round.
+        let toast = match action {
+            Action::Undo => Update::toast(id, &format!("Undone: {}", label), Action::Redo),
+            Action::Redo => Update::toast(id, label, Action::Undo),
+        };
+        self.broadcast(client_id, toast);
+        Ok(list)
+    }
+
+    /// Remembers the category of `item` for items with the same name.
+    async fn remember_category(&self, item: &Item) {
+        let changed = self
+            .categories
+            .lock()
+            .unwrap()
+            .remember(&item.name, item.category.as_deref());
+        if changed {
+            self.persist_categories().await;
+        }
+    }
+
+    /// Every category items have been filed under, for suggestions.
+    pub fn categories(&self) -> Vec<String> {
+        self.categories.lock().unwrap().names()
+    }
+
+    pub fn store_layouts(&self) -> Vec<StoreLayout> {
+        self.store_layouts.lock().unwrap().clone()
+    }
+
+    pub fn store_layout(&self, store_id: &str) -> Result<StoreLayout, Error> {
+        self.store_layouts
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|layout| layout.id == store_id)
+            .cloned()
+            .ok_or(Error::StoreNotFound)
+    }
+
+    pub async fn create_store_layout(&self, new: NewStoreLayout) -> Result<StoreLayout, Error> {
+        let layout = StoreLayout::new(non_empty(&new.name)?, walk_path(new.categories));
+        self.store_layouts.lock().unwrap().push(layout.clone());
+        self.persist_store_layouts().await;
+        Ok(layout)
+    }
+
+    /// Renames a store layout and/or replaces its walk path. Lists shopped
+    /// at the store are reloaded, as their order may have changed.
+    pub async fn update_store_layout(
+        &self,
+        client_id: &str,
+        store_id: &str,
+        patch: StoreLayoutPatch,
+    ) -> Result<StoreLayout, Error> {
+        let name = patch.name.as_deref().map(non_empty).transpose()?;
+        let layout = {
+            let mut layouts = self.store_layouts.lock().unwrap();
+            let layout = layouts
+                .iter_mut()
+                .find(|layout| layout.id == store_id)
+                .ok_or(Error::StoreNotFound)?;
+            if let Some(name) = name {
+                layout.name = name;
+            }
+            if let Some(categories) = patch.categories {
+                layout.categories = walk_path(categories);
+            }
+            layout.clone()
+        };
+        self.persist_store_layouts().await;
+        for id in self.lists_shopped_at(store_id) {
+            self.broadcast(client_id, Update::Reload { list_id: Some(id) });
+        }
+        Ok(layout)
+    }
+
+    /// Deletes a store layout. Lists that were shopped there are no longer
+    /// tied to a store; that cannot be undone, as the store is gone.
+    pub async fn delete_store_layout(
+        &self,
+        client_id: &str,
+        store_id: &str,
+    ) -> Result<StoreLayout, Error> {
+        let layout = {
+            let mut layouts = self.store_layouts.lock().unwrap();
+            let index = layouts
+                .iter()
+                .position(|layout| layout.id == store_id)
+                .ok_or(Error::StoreNotFound)?;
+            layouts.remove(index)
+        };
+        self.persist_store_layouts().await;
+        for id in self.lists_shopped_at(store_id) {
+            // The list may have been deleted in the meantime.
+            let cleared = self
+                .state
+                .write()
+                .unwrap()
+                .get_mut(&id)
+                .map(|list| list.store = None)
+                .is_some();
+            if cleared {
+                self.persist(client_id, &id, "update_list", "").await;
+                self.broadcast(client_id, Update::Reload { list_id: Some(id) });
+            }
+        }
+        Ok(layout)
+    }
+
+    pub fn templates(&self) -> Vec<ListTemplate> {
+        self.templates.lock().unwrap().clone()
+    }
+
+    pub fn template(&self, template_id: &str) -> Result<ListTemplate, Error> {
+        self.templates
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|template| template.id == template_id)
+            .cloned()
+            .ok_or(Error::TemplateNotFound)
+    }
+
+    /// Saves the items of list `list_id` as a template, named `name` or
+    /// after the list. A template of the same name is replaced, keeping its
+    /// id, so saving a list again updates its template.
+    pub async fn save_template(
+        &self,
+        list_id: &str,
+        name: Option<&str>,
+    ) -> Result<ListTemplate, Error> {
+        let name = name.map(non_empty).transpose()?;
+        let list = self.list(list_id)?;
+        let name = name.unwrap_or(list.name);
+        let template = {
+            let mut templates = self.templates.lock().unwrap();
+            match templates
+                .iter_mut()
+                .find(|template| template.name.eq_ignore_ascii_case(&name))
+            {
+                Some(template) => {
+                    template.name = name;
+                    template.items = list.items;
+                    template.clone()
+                }
+                None => {
+                    let template = ListTemplate::new(name, list.items);
+                    templates.push(template.clone());
+                    template
+                }
+            }
+        };
+        self.persist_templates().await;
+        Ok(template)
+    }
+
+    pub async fn delete_template(&self, template_id: &str) -> Result<ListTemplate, Error> {
+        let template = {
+            let mut templates = self.templates.lock().unwrap();
+            let index = templates
+                .iter()
+                .position(|template| template.id == template_id)
+                .ok_or(Error::TemplateNotFound)?;
+            templates.remove(index)
+        };
+        self.persist_templates().await;
+        Ok(template)
+    }
+
+    fn lists_shopped_at(&self, store_id: &str) -> Vec<String> {
+        self.state
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, list)| list.store.as_deref() == Some(store_id))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Moves items removed from list `id`, with their former positions, to
+    /// the trash.
+    async fn discard(&self, id: &str, items: Vec<(usize, Item)>) {
+        if items.is_empty() {
+            return;
+        }
+        self.trash.lock().unwrap().extend(
+            items
+                .into_iter()
+                .map(|(position, item)| TrashEntry::new(id, Trashed::Item { item, position })),
+        );
+        self.persist_trash().await;
+    }
+
+    /// The trash, most recently deleted first.
+    pub fn trash(&self) -> Vec<TrashEntry> {
+        let mut trash = self.trash.lock().unwrap().clone();
+        trash.reverse();
+        trash
+    }
+
+    /// Puts a deleted list or item back. A list whose id has been taken by a
+    /// new list in the meantime comes back under a fresh id; the returned
+    /// entry says where it went.
+    pub async fn restore(&self, client_id: &str, entry_id: &str) -> Result<TrashEntry, Error> {
+        let mut entry = self.take_from_trash(entry_id)?;
+
+        match &entry.content {
+            Trashed::List { list } => {
+                {
+                    let mut lists = self.state.write().unwrap();
+                    if lists.contains_key(&entry.list_id) {
+                        entry.list_id = slug::unique_slug(&list.name, |id| lists.contains_key(id));
+                    }
+                    lists.insert(entry.list_id.clone(), list.clone());
+                }
+                self.persist(client_id, &entry.list_id, "restore", &list.name)
+                    .await;
+                self.broadcast(client_id, Update::list_created(&entry.list_id, list));
+            }
+            Trashed::Item { item, position } => {
+                let restored = self.with_list(&entry.list_id, "Item restored", |list| {
+                    // Undo may have put it back already.
+                    if !list.items.iter().any(|i| i.id == item.id) {
+                        let position = (*position).min(list.items.len());
+                        list.items.insert(position, item.clone());
+                    }
+                    Ok(())
+                });
+                if let Err(err) = restored {
+                    self.trash.lock().unwrap().push(entry);
+                    return Err(err);
+                }
+                self.persist(client_id, &entry.list_id, "restore", &item.name)
+                    .await;
+                self.broadcast(
+                    client_id,
+                    Update::Reload {
+                        list_id: Some(entry.list_id.clone()),
+                    },
+                );
+            }
+        }
+
+        self.persist_trash().await;
+        Ok(entry)
+    }
+
+    /// Deletes a trash entry for good.
+    pub async fn purge(&self, entry_id: &str) -> Result<TrashEntry, Error> {
+        let entry = self.take_from_trash(entry_id)?;
+        self.persist_trash().await;
+        Ok(entry)
+    }
+
+    pub async fn empty_trash(&self) {
+        self.trash.lock().unwrap().clear();
+        self.persist_trash().await;
+    }
+
+    /// Deletes every trash entry older than `retention` for good.
+    pub async fn purge_expired(&self, retention: Duration) {
+        let cutoff = models::now().saturating_sub(retention.as_secs());
+        let purged = {
+            let mut trash = self.trash.lock().unwrap();
+            let before = trash.len();
+            trash.retain(|entry| entry.deleted_at > cutoff);
+            before != trash.len()
+        };
+        if purged {
+            self.persist_trash().await;
+        }
+    }
+
+    /// Changes to list `id` from the log, most recent first.
+    pub async fn changes_to(&self, id: &str) -> Result<Vec<Change>, Error> {
+        let mut changes = self
+            .store
+            .read_changes(Some(id), u64::MAX)
+            .await
+            .map_err(storage)?;
+        changes.reverse();
+        Ok(changes)
+    }
+
+    /// List `id` as it was at `at`, in milliseconds since the Unix epoch,
+    /// rebuilt from the log; `None` when it did not exist then.
+    pub async fn list_at(&self, id: &str, at: u64) -> Result<Option<GroceryList>, Error> {
+        let log = self.store.read_log(Some(id), at).await.map_err(storage)?;
+        Ok(log.state_at(at).remove(id))
+    }
+
+    /// Every list as it was at `at`, rebuilt from the log.
+    pub async fn lists_at(&self, at: u64) -> Result<Lists, Error> {
+        let log = self.store.read_log(None, at).await.map_err(storage)?;
+        Ok(log.state_at(at))
+    }
+
+    fn take_from_trash(&self, entry_id: &str) -> Result<TrashEntry, Error> {
+        let mut trash = self.trash.lock().unwrap();
+        let index = trash
+            .iter()
+            .position(|entry| entry.id == entry_id)
+            .ok_or(Error::EntryNotFound)?;
+        Ok(trash.remove(index))
+    }
+}
+
+/// Keeps the trash in step with an undo or redo that turned `before` into
+/// `after`: items it removed go to the trash, items it brought back leave it.
+fn sync_trash(trash: &mut Vec<TrashEntry>, id: &str, before: &GroceryList, after: &GroceryList) {
+    trash.retain(|entry| match &entry.content {
+        Trashed::Item { item, .. } => {
+            entry.list_id != id
+                || before.items.iter().any(|i| i.id == item.id)
+                || !after.items.iter().any(|i| i.id == item.id)
+        }
+        Trashed::List { .. } => true,
+    });
+    for (position, item) in before.items.iter().enumerate() {
+        if !after.items.iter().any(|i| i.id == item.id) {
+            trash.push(TrashEntry::new(
+                id,
+                Trashed::Item {
+                    item: item.clone(),
+                    position,
+                },
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::store::SqliteStore;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    async fn context(name: &str) -> AppContext {
+        let dir = std::env::temp_dir().join(format!("yala-ops-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        open(&dir.join("lists.db")).await
+    }
+
+    async fn open(path: &Path) -> AppContext {
+        let store = Arc::new(SqliteStore::open(path, path.with_extension("json")).unwrap());
+        AppContext::with_store(store, Config::default())
+            .await
+            .unwrap()
+    }
+
+    fn names(ctx: &AppContext, id: &str) -> Vec<String> {
+        let list = ctx.list(id).unwrap();
+        list.items.into_iter().map(|item| item.name).collect()
+    }
+
+    fn trashed(ctx: &AppContext) -> Vec<String> {
+        ctx.trash()
+            .into_iter()
+            .map(|entry| match entry.content {
+                Trashed::Item { item, .. } => item.name,
+                Trashed::List { list } => list.name,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn restores_a_list_under_a_fresh_id_when_its_own_is_taken() {
+        let ctx = context("restore").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        ctx.add_item("test", &id, "milk").await.unwrap();
+        ctx.delete_list("test", &id).await.unwrap();
+        let (new_id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        assert_eq!(new_id, id);
+
+        let entry = ctx.trash()[0].id.clone();
+        let restored = ctx.restore("test", &entry).await.unwrap();
+        assert_eq!(restored.list_id, "groceries-2");
+        assert_eq!(names(&ctx, "groceries-2"), ["milk"]);
+        assert!(names(&ctx, &id).is_empty());
+        assert!(ctx.trash().is_empty());
+    }
+
+    #[tokio::test]
+    async fn undo_and_redo_move_items_out_of_and_into_the_trash() {
+        let ctx = context("undo-trash").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        let (item, _) = ctx.add_item("test", &id, "milk").await.unwrap();
+        ctx.delete_item("test", &id, &item.id).await.unwrap();
+        assert_eq!(trashed(&ctx), ["milk"]);
+
+        ctx.undo("test", &id).await.unwrap();
+        assert_eq!(names(&ctx, &id), ["milk"]);
+        assert!(trashed(&ctx).is_empty());
+
+        ctx.redo("test", &id).await.unwrap();
+        assert!(names(&ctx, &id).is_empty());
+        assert_eq!(trashed(&ctx), ["milk"]);
+    }
+
+    #[tokio::test]
+    async fn purging_with_no_retention_empties_the_trash() {
+        let ctx = context("purge").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        let (item, _) = ctx.add_item("test", &id, "milk").await.unwrap();
+        ctx.delete_item("test", &id, &item.id).await.unwrap();
+
+        ctx.purge_expired(Duration::ZERO).await;
+        assert!(ctx.trash().is_empty());
+    }
+
+    #[tokio::test]
+    async fn clearing_nothing_changes_nothing() {
+        let ctx = context("clear").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        ctx.add_item("test", &id, "milk").await.unwrap();
+        let (_, mut rx) = ctx.updates.subscribe(None);
+
+        assert!(ctx.clear_completed("test", &id).await.unwrap().is_empty());
+        assert!(rx.try_recv().is_err());
+        assert_eq!(ctx.undo("test", &id).await.unwrap().items.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn stepping_below_the_minimum_changes_nothing() {
+        let ctx = context("step").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        let (item, _) = ctx.add_item("test", &id, "milk").await.unwrap();
+        let (_, mut rx) = ctx.updates.subscribe(None);
+
+        let stepped = ctx
+            .step_quantity("test", &id, &item.id, false)
+            .await
+            .unwrap();
+        assert_eq!(stepped.quantity, None);
+        assert!(rx.try_recv().is_err());
+        assert_eq!(ctx.changes_to(&id).await.unwrap()[0].op, "add_item");
+    }
+
+    #[tokio::test]
+    async fn files_new_items_under_the_category_their_name_was_given() {
+        let ctx = context("categories").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        let (milk, _) = ctx.add_item("test", &id, "Milk").await.unwrap();
+        let patch = ItemPatch {
+            category: Some(Some("Dairy".into())),
+            ..ItemPatch::default()
+        };
+        ctx.update_item("test", &id, &milk.id, patch).await.unwrap();
+        assert_eq!(ctx.categories(), ["Dairy"]);
+
+        let (other_id, _) = ctx.create_list("test", "Weekly").await.unwrap();
+        let (again, _) = ctx.add_item("test", &other_id, " MILK x2").await.unwrap();
+        assert_eq!(again.category.as_deref(), Some("Dairy"));
+        let (other, _) = ctx.add_item("test", &id, "eggs").await.unwrap();
+        assert_eq!(other.category, None);
+    }
+
+    #[tokio::test]
+    async fn undoing_a_move_puts_the_items_back_in_one_step() {
+        let ctx = context("move").await;
+        let (a, _) = ctx.create_list("test", "A").await.unwrap();
+        let (b, _) = ctx.create_list("test", "B").await.unwrap();
+        let (item, _) = ctx.add_item("test", &a, "batteries").await.unwrap();
+        ctx.transfer_items("test", &a, &b, &[item.id], false)
+            .await
+            .unwrap();
+        assert!(names(&ctx, &a).is_empty());
+        assert_eq!(names(&ctx, &b), ["batteries"]);
+
+        ctx.undo("test", &a).await.unwrap();
+        assert_eq!(names(&ctx, &a), ["batteries"]);
+        assert!(names(&ctx, &b).is_empty());
+        assert!(ctx.trash().is_empty());
+        drop(ctx);
+
+        let path = std::env::temp_dir()
+            .join(format!("yala-ops-move-{}", std::process::id()))
+            .join("lists.db");
+        let ctx = open(&path).await;
+        assert_eq!(names(&ctx, &a), ["batteries"]);
+        assert!(names(&ctx, &b).is_empty());
+    }
+
+    #[tokio::test]
+    async fn redoing_a_move_takes_the_items_across_again() {
+        let ctx = context("move-redo").await;
+        let (a, _) = ctx.create_list("test", "A").await.unwrap();
+        let (b, _) = ctx.create_list("test", "B").await.unwrap();
+        let (item, _) = ctx.add_item("test", &a, "batteries").await.unwrap();
+        ctx.transfer_items("test", &a, &b, &[item.id], false)
+            .await
+            .unwrap();
+
+        ctx.undo("test", &a).await.unwrap();
+        ctx.redo("test", &a).await.unwrap();
+        assert!(names(&ctx, &a).is_empty());
+        assert_eq!(names(&ctx, &b), ["batteries"]);
+    }
+
+    #[tokio::test]
+    async fn duplicates_get_fresh_item_ids_and_a_slug_of_their_own() {
+        let ctx = context("duplicate").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        ctx.add_item("test", &id, "milk").await.unwrap();
+
+        let (copy_id, copy) = ctx
+            .duplicate_list("test", &id, Some("Groceries"), false)
+            .await
+            .unwrap();
+        assert_eq!(copy_id, "groceries-2");
+        let (default_id, default) = ctx.duplicate_list("test", &id, None, false).await.unwrap();
+        assert_eq!(default_id, "groceries-copy");
+        assert_eq!(default.name, "Groceries (copy)");
+
+        let original = ctx.list(&id).unwrap();
+        assert_eq!(names(&ctx, &copy_id), ["milk"]);
+        assert_ne!(copy.items[0].id, original.items[0].id);
+        assert_ne!(default.items[0].id, original.items[0].id);
+    }
+
+    #[tokio::test]
+    async fn resetting_unchecks_the_copied_items() {
+        let ctx = context("reset").await;
+        let (id, _) = ctx.create_list("test", "Groceries").await.unwrap();
+        let (milk, _) = ctx.add_item("test", &id, "milk").await.unwrap();
+        let patch = ItemPatch {
+            completed: Some(true),
+            ..ItemPatch::default()
+        };
+        ctx.update_item("test", &id, &milk.id, patch).await.unwrap();
+
+        let (_, kept) = ctx.duplicate_list("test", &id, None, false).await.unwrap();
+        assert!(kept.items[0].completed);
+        let (_, reset) = ctx.duplicate_list("test", &id, None, true).await.unwrap();
+        assert!(!reset.items[0].completed);
+
+        let template = ctx.save_template(&id, None).await.unwrap();
+        let (_, fresh) = ctx
+            .create_from_template("test", "Weekly", &template.id, true)
+            .await
+            .unwrap();
+        assert!(!fresh.items[0].completed);
+    }
+
+    #[tokio::test]
+    async fn a_deleted_template_is_not_found() {
+        let ctx = context("template").await;
+        let (id, _) = ctx.create_list("test", "Camping trip").await.unwrap();
+        ctx.add_item("test", &id, "tent").await.unwrap();
+        let template = ctx.save_template(&id, None).await.unwrap();
+        ctx.delete_template(&template.id).await.unwrap();
+
+        let created = ctx
+            .create_from_template("test", "Camping", &template.id, false)
+            .await;
+        assert_eq!(created.err(), Some(Error::TemplateNotFound));
+        assert_eq!(
+            ctx.template(&template.id).err(),
+            Some(Error::TemplateNotFound)
+        );
+        assert_eq!(
+            ctx.delete_template(&template.id).await.err(),
+            Some(Error::TemplateNotFound)
+        );
+        assert!(ctx.templates().is_empty());
+    }
+}
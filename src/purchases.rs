@@ -0,0 +1,153 @@
+use crate::models::{GroceryList, Item};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// How many purchases are remembered per list.
+const LIMIT: usize = 40;
+
+/// What was bought from each list: items that were checked off or cleared,
+/// most recent first, one entry per name. Kept apart from the lists so it
+/// outlives the items themselves.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Purchases(BTreeMap<String, Vec<Purchase>>);
+
+/// An item as it was when it was last bought, enough to put it back on the
+/// list as it was.
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+pub struct Purchase {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// When it was bought, in milliseconds since the epoch.
+    pub at: u64,
+}
+
+impl Purchase {
+    /// A fresh, unchecked item like the one that was bought.
+    pub fn to_item(&self) -> Item {
+        Item {
+            quantity: self.quantity,
+            unit: self.unit.clone(),
+            category: self.category.clone(),
+            ..Item::new(self.name.clone())
+        }
+    }
+}
+
+impl Purchases {
+    /// Notes that `item` was bought from list `list_id` at `at`, replacing
+    /// an earlier purchase of the same name.
+    pub fn record(&mut self, list_id: &str, item: &Item, at: u64) {
+        let purchases = self.0.entry(list_id.to_string()).or_default();
+        purchases.retain(|purchase| !purchase.name.eq_ignore_ascii_case(&item.name));
+        purchases.insert(
+            0,
+            Purchase {
+                name: item.name.clone(),
+                quantity: item.quantity,
+                unit: item.unit.clone(),
+                category: item.category.clone(),
+                at,
+            },
+        );
+        purchases.truncate(LIMIT);
+    }
+
+    /// Purchases from list `list_id`, most recent first.
+    pub fn get(&self, list_id: &str) -> &[Purchase] {
+        self.0.get(list_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The last purchase of `name` from list `list_id`, ignoring case.
+    pub fn find(&self, list_id: &str, name: &str) -> Option<&Purchase> {
+        self.get(list_id)
+            .iter()
+            .find(|purchase| purchase.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<Purchase>)> {
+        self.0.iter()
+    }
+
+    pub fn insert(&mut self, list_id: String, purchases: Vec<Purchase>) {
+        self.0.insert(list_id, purchases);
+    }
+}
+
+/// The purchases whose name `list` does not have among its unchecked items.
+pub fn not_on<'a>(list: &GroceryList, purchases: &'a [Purchase]) -> Vec<&'a Purchase> {
+    purchases
+        .iter()
+        .filter(|purchase| {
+            !list
+                .items
+                .iter()
+                .any(|item| !item.completed && item.name.eq_ignore_ascii_case(&purchase.name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(purchases: &[Purchase]) -> Vec<&str> {
+        purchases.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_the_latest_purchase_of_each_name_first() {
+        let mut purchases = Purchases::default();
+        let mut milk = Item::new("milk".into());
+        purchases.record("groceries", &milk, 1);
+        purchases.record("groceries", &Item::new("eggs".into()), 2);
+        milk.name = "Milk".into();
+        milk.quantity = Some(2.0);
+        purchases.record("groceries", &milk, 3);
+
+        assert_eq!(names(purchases.get("groceries")), ["Milk", "eggs"]);
+        let found = purchases.find("groceries", " MILK ").unwrap();
+        assert_eq!((found.quantity, found.at), (Some(2.0), 3));
+        assert!(purchases.get("hardware").is_empty());
+    }
+
+    #[test]
+    fn remembers_only_the_latest_forty() {
+        let mut purchases = Purchases::default();
+        for n in 0..50 {
+            purchases.record("groceries", &Item::new(format!("item {n}")), n);
+        }
+        let kept = purchases.get("groceries");
+        assert_eq!(kept.len(), LIMIT);
+        assert_eq!(kept[0].name, "item 49");
+        assert_eq!(kept[LIMIT - 1].name, "item 10");
+    }
+
+    #[test]
+    fn offers_again_only_what_is_not_still_to_buy() {
+        let mut purchases = Purchases::default();
+        for name in ["milk", "eggs", "bread"] {
+            purchases.record("groceries", &Item::new(name.into()), 0);
+        }
+        let mut eggs = Item::new("Eggs".into());
+        eggs.completed = true;
+        let list = GroceryList {
+            name: "Groceries".into(),
+            items: vec![Item::new("MILK".into()), eggs],
+            show_completed: true,
+            store: None,
+        };
+
+        let offered: Vec<&str> = not_on(&list, purchases.get("groceries"))
+            .into_iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(offered, ["bread", "eggs"]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Quantities typed into the quick-add field, such as "2x milk",
+//! "500 g flour" or "eggs x6".
+
+/// Units recognised after a number. Anything else after a number is taken
+/// to be part of the name, so "3 eggs" is three of "eggs".
+const UNITS: &[&str] = &[
+    "mg", "g", "kg", "ml", "cl", "dl", "l", "oz", "lb", "lbs", "tsp", "tbsp", "cup", "cups", "pc",
+    "pcs", "pack", "packs", "can", "cans", "bottle", "bottles", "jar", "jars", "bag", "bags",
+    "box", "boxes",
+];
+
+/// The result of splitting the quick-add input.
+#[derive(Debug, PartialEq)]
+pub struct Parsed {
+    pub name: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+}
+
+/// Splits a quantity and unit off the start of `input` ("2x milk", "2 x milk",
+/// "500 g flour", "500g flour", "1.5 l milk", "3 eggs") or a count off its end
+/// ("milk x2", "milk 2x"). Input without a quantity, or with nothing left
+/// for a name, is returned unchanged as the name. So are names that start
+/// with a number, such as "7 Up": a bare number followed by a single
+/// capitalised word is read as a brand rather than a count.
+pub fn parse(input: &str) -> Parsed {
+    let input = input.trim();
+    let unchanged = || Parsed {
+        name: input.to_string(),
+        quantity: None,
+        unit: None,
+    };
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if words.len() < 2 {
+        return unchanged();
+    }
+
+    if let Some((quantity, unit, rest)) = leading(&words)
+        && !rest.is_empty()
+    {
+        return Parsed {
+            name: rest.join(" "),
+            quantity: Some(quantity),
+            unit,
+        };
+    }
+
+    let (last, rest) = words.split_last().unwrap();
+    if let Some(quantity) = count(last) {
+        return Parsed {
+            name: rest.join(" "),
+            quantity: Some(quantity),
+            unit: None,
+        };
+    }
+
+    unchanged()
+}
+
+/// Reads "2x", "2 x", "500 g", "500g" or a bare "3" from the first words.
+fn leading<'a>(words: &'a [&'a str]) -> Option<(f64, Option<String>, &'a [&'a str])> {
+    let first = words[0];
+    if let Some(quantity) = count(first) {
+        return Some((quantity, None, &words[1..]));
+    }
+
+    let split = first
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(first.len());
+    let quantity = number(&first[..split])?;
+    let glued = &first[split..];
+
+    if glued.is_empty() {
+        let next = words.get(1).copied().unwrap_or_default();
+        if matches!(next, "x" | "×") {
+            return Some((quantity, None, &words[2..]));
+        }
+        if let Some(unit) = unit(next) {
+            return Some((quantity, Some(unit), &words[2..]));
+        }
+        if words.len() == 2 && next.starts_with(char::is_uppercase) {
+            return None;
+        }
+        return Some((quantity, None, &words[1..]));
+    }
+    unit(glued).map(|unit| (quantity, Some(unit), &words[1..]))
+}
+
+/// Reads a count written as "2x", "x2", "2×" or "×2".
+fn count(word: &str) -> Option<f64> {
+    let digits = word
+        .strip_suffix(['x', '×'])
+        .or_else(|| word.strip_prefix(['x', '×']))?;
+    number(digits)
+}
+
+fn number(text: &str) -> Option<f64> {
+    let quantity: f64 = text.replace(',', ".").parse().ok()?;
+    (quantity.is_finite() && quantity > 0.0).then_some(quantity)
+}
+
+fn unit(word: &str) -> Option<String> {
+    let word = word.to_lowercase();
+    UNITS.contains(&word.as_str()).then_some(word)
+}
+
+/// How much the + and - buttons change a quantity in `unit`.
+pub fn step(unit: Option<&str>) -> f64 {
+    match unit {
+        Some("mg" | "g" | "ml") => 100.0,
+        Some("kg" | "l" | "lb" | "lbs") => 0.5,
+        _ => 1.0,
+    }
+}
+
+/// Formats a quantity without trailing zeros, e.g. "2" or "1.5".
+pub fn format_number(quantity: f64) -> String {
+    if quantity.fract() == 0.0 {
+        format!("{}", quantity)
+    } else {
+        format!("{:.2}", quantity)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+/// Formats a quantity for the badge: "2×" without a unit, "500 g" with one.
+pub fn format(quantity: f64, unit: Option<&str>) -> String {
+    let number = format_number(quantity);
+    match unit {
+        Some(unit) => format!("{} {}", number, unit),
+        None => format!("{}×", number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(name: &str, quantity: Option<f64>, unit: Option<&str>) -> Parsed {
+        Parsed {
+            name: name.to_string(),
+            quantity,
+            unit: unit.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn reads_counts() {
+        assert_eq!(parse("2x milk"), parsed("milk", Some(2.0), None));
+        assert_eq!(parse("2 x milk"), parsed("milk", Some(2.0), None));
+        assert_eq!(parse("milk x2"), parsed("milk", Some(2.0), None));
+        assert_eq!(parse("milk 2x"), parsed("milk", Some(2.0), None));
+        assert_eq!(parse("3 eggs"), parsed("eggs", Some(3.0), None));
+        assert_eq!(
+            parse("3 Free range eggs"),
+            parsed("Free range eggs", Some(3.0), None)
+        );
+    }
+
+    #[test]
+    fn reads_units() {
+        assert_eq!(
+            parse("500 g flour"),
+            parsed("flour", Some(500.0), Some("g"))
+        );
+        assert_eq!(parse("500g flour"), parsed("flour", Some(500.0), Some("g")));
+        assert_eq!(parse("1.5 l milk"), parsed("milk", Some(1.5), Some("l")));
+        assert_eq!(parse("1,5 L milk"), parsed("milk", Some(1.5), Some("l")));
+        assert_eq!(
+            parse("2 Cans Tomatoes"),
+            parsed("Tomatoes", Some(2.0), Some("cans"))
+        );
+    }
+
+    #[test]
+    fn keeps_names_starting_with_a_number() {
+        assert_eq!(parse("7 Up"), parsed("7 Up", None, None));
+        assert_eq!(parse("3 Musketeers"), parsed("3 Musketeers", None, None));
+        assert_eq!(parse("2x 7 Up"), parsed("7 Up", Some(2.0), None));
+    }
+
+    #[test]
+    fn leaves_input_without_a_quantity() {
+        assert_eq!(parse("milk"), parsed("milk", None, None));
+        assert_eq!(parse("2x"), parsed("2x", None, None));
+        assert_eq!(parse("500 g"), parsed("500 g", None, None));
+        assert_eq!(parse("0 eggs"), parsed("0 eggs", None, None));
+        assert_eq!(parse("  oat milk  "), parsed("oat milk", None, None));
+    }
+}
@@ -0,0 +1,72 @@
+use deunicode::deunicode;
+
+/// Slugs that would read like an action or clash with a route.
+const RESERVED: &[&str] = &["new", "create", "edit", "delete", "events", "api"];
+
+/// Turns a list name into a URL-safe id: the name is transliterated to ASCII
+/// and lowercased, and every run of other characters becomes a single `-`.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in deunicode(name).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "list".to_string()
+    } else if RESERVED.contains(&slug) {
+        format!("{}-list", slug)
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Like `slugify`, but appends `-2`, `-3`, … until the slug is not `taken`.
+pub fn unique_slug(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let base = slugify(name);
+    if !taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|slug| !taken(slug))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transliterates_and_collapses_separators() {
+        assert_eq!(slugify("Crème Brûlée & Co."), "creme-brulee-co");
+        assert_eq!(slugify("Ärger  mit   Öl"), "arger-mit-ol");
+        assert_eq!(slugify("  Weekend!! "), "weekend");
+    }
+
+    #[test]
+    fn suffixes_reserved_words_and_falls_back_for_empty_names() {
+        assert_eq!(slugify("New"), "new-list");
+        assert_eq!(slugify("API"), "api-list");
+        assert_eq!(slugify("!!!"), "list");
+        assert_eq!(slugify(""), "list");
+    }
+
+    #[test]
+    fn counts_up_until_the_slug_is_free() {
+        let taken = ["groceries", "groceries-2"];
+        assert_eq!(unique_slug("Hardware", |s| taken.contains(&s)), "hardware");
+        assert_eq!(
+            unique_slug("Groceries", |s| taken.contains(&s)),
+            "groceries-3"
+        );
+        assert_eq!(
+            unique_slug("Groceries", |s| s == "groceries"),
+            "groceries-2"
+        );
+    }
+}
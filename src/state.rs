@@ -1,65 +1,238 @@
-use crate::models::GroceryList;
-use notify::{RecursiveMode, Result as NotifyResult, Watcher};
+use crate::categories::Categories;
+use crate::changelog::{self, Change, Changelog, Edit, Snapshot};
+use crate::config::Config;
+use crate::events::{Broadcaster, Update};
+use crate::history::History;
+use crate::models::{GroceryList, ListTemplate, StoreLayout, TrashEntry};
+use crate::purchases::Purchases;
+use crate::store::{self, Lists, Store};
+use crate::suggestions::NameIndex;
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    io,
+    sync::{Arc, Mutex, RwLock},
     time::Duration,
 };
-use tokio::fs;
-use tokio::sync::broadcast;
-
-const STORE: &'static str = "lists.json";
 
 pub type AppState = Arc<RwLock<HashMap<String, GroceryList>>>;
 
 #[derive(Clone)]
 pub struct AppContext {
     pub state: AppState,
-    pub update_tx: broadcast::Sender<String>,
+    pub updates: Arc<Broadcaster>,
+    pub store: Arc<dyn Store>,
+    pub config: Arc<Config>,
+    pub history: Arc<Mutex<History>>,
+    pub trash: Arc<Mutex<Vec<TrashEntry>>>,
+    pub categories: Arc<Mutex<Categories>>,
+    pub names: Arc<Mutex<NameIndex>>,
+    pub purchases: Arc<Mutex<Purchases>>,
+    pub store_layouts: Arc<Mutex<Vec<StoreLayout>>>,
+    pub templates: Arc<Mutex<Vec<ListTemplate>>>,
+    /// Every list as the change log last recorded it, which the next change
+    /// is diffed against. Held while writing to the log, so compaction
+    /// cannot lose a change appended while it runs.
+    pub logged: Arc<tokio::sync::Mutex<Lists>>,
 }
 
 impl AppContext {
-    pub async fn new() -> Self {
-        let state = Arc::new(RwLock::new(load_data().await));
-        let (update_tx, _) = broadcast::channel(100);
+    pub async fn new(config: Config) -> io::Result<Self> {
+        let store = store::open(&config)?;
+        Self::with_store(store, config).await
+    }
 
-        tokio::spawn(watch_file(update_tx.clone()));
+    /// Fails instead of starting empty when the store cannot be read, so a
+    /// damaged file is never overwritten by the first change.
+    pub async fn with_store(store: Arc<dyn Store>, config: Config) -> io::Result<Self> {
+        let mut lists = store.load().await?;
+        for (id, list) in lists.iter_mut() {
+            if list.assign_missing_ids() {
+                store.save_list(id, list).await?;
+            }
+        }
+        let trash = store.load_trash().await?;
+        let categories = store.load_categories().await?;
+        let names = store.load_names().await?;
+        let purchases = store.load_purchases().await?;
+        let store_layouts = store.load_store_layouts().await?;
+        let templates = store.load_templates().await?;
 
-        Self { state, update_tx }
-    }
-}
+        // Lists that existed before the log did are its starting point.
+        let log = store.read_log(None, u64::MAX).await?;
+        let logged = if log.snapshot.is_none() && log.changes.is_empty() {
+            if !lists.is_empty() {
+                let snapshot = Snapshot {
+                    at: changelog::now_ms(),
+                    lists: lists.clone(),
+                };
+                store
+                    .write_log(&Changelog {
+                        snapshot: Some(snapshot),
+                        changes: vec![],
+                    })
+                    .await?;
+            }
+            lists.clone()
+        } else {
+            log.state_at(u64::MAX)
+        };
 
-async fn load_data() -> HashMap<String, GroceryList> {
-    fs::read_to_string(STORE)
-        .await
-        .ok()
-        .and_then(|s| serde_json::from_str(&s).ok())
-        .unwrap_or_default()
-}
+        let state = Arc::new(RwLock::new(lists));
+        let updates = Arc::new(Broadcaster::new(
+            config.channel_capacity,
+            config.event_backlog,
+        ));
 
-pub async fn save_data(state: &AppState) {
-    let data = state.read().unwrap().clone();
-    if let Ok(json) = serde_json::to_string_pretty(&data) {
-        let _ = fs::write(STORE, json).await;
+        let ctx = Self {
+            state,
+            updates,
+            store,
+            config: Arc::new(config),
+            history: Arc::default(),
+            trash: Arc::new(Mutex::new(trash)),
+            categories: Arc::new(Mutex::new(categories)),
+            names: Arc::new(Mutex::new(names)),
+            purchases: Arc::new(Mutex::new(purchases)),
+            store_layouts: Arc::new(Mutex::new(store_layouts)),
+            templates: Arc::new(Mutex::new(templates)),
+            logged: Arc::new(tokio::sync::Mutex::new(logged)),
+        };
+        tokio::spawn(ctx.store.watch(ctx.clone()));
+        if let Some(retention) = ctx.config.trash_retention {
+            tokio::spawn(ctx.clone().expire_trash(retention));
+        }
+        if let Some(retention) = ctx.config.log_retention {
+            tokio::spawn(ctx.clone().compact_log(retention));
+        }
+        Ok(ctx)
     }
-}
 
-async fn watch_file(tx: broadcast::Sender<String>) {
-    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(100);
+    /// Purges trash entries older than `retention`, right away and then
+    /// once an hour.
+    async fn expire_trash(self, retention: Duration) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            self.purge_expired(retention).await;
+        }
+    }
 
-    let mut watcher = notify::recommended_watcher(move |res: NotifyResult<notify::Event>| {
-        if let Ok(event) = res {
-            if event.kind.is_modify() {
-                let _ = notify_tx.blocking_send(());
+    /// Folds changes older than `retention` into a snapshot, right away and
+    /// then once a day.
+    async fn compact_log(self, retention: Duration) {
+        let mut interval = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+        loop {
+            interval.tick().await;
+            let before = changelog::now_ms().saturating_sub(retention.as_millis() as u64);
+            if let Err(err) = self.compact(before).await {
+                eprintln!("Failed to compact the change log: {}", err);
             }
         }
-    })
-    .unwrap();
+    }
+
+    async fn compact(&self, before: u64) -> io::Result<()> {
+        let _logged = self.logged.lock().await;
+        let mut log = self.store.read_log(None, u64::MAX).await?;
+        if log.compact(before) {
+            self.store.write_log(&log).await?;
+        }
+        Ok(())
+    }
 
-    let _ = watcher.watch(std::path::Path::new(STORE), RecursiveMode::NonRecursive);
+    /// Sends `update` to every connected client. `client_id` identifies the
+    /// client that made the change, so it can skip applying it twice.
+    pub fn broadcast(&self, client_id: &str, update: Update) {
+        self.updates.send(update.encode(client_id));
+    }
 
-    while let Some(_) = notify_rx.recv().await {
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        let _ = tx.send(String::new());
+    /// Writes the current in-memory version of list `id` to the store, or
+    /// removes it from the store when it no longer exists in memory, and
+    /// appends the change to the log as operation `op` on `detail`.
+    pub async fn persist(&self, client_id: &str, id: &str, op: &str, detail: &str) {
+        let list = self.state.read().unwrap().get(id).cloned();
+        let result = match &list {
+            Some(list) => self.store.save_list(id, list).await,
+            None => self.store.delete_list(id).await,
+        };
+        if let Err(err) = result {
+            eprintln!("Failed to persist list {}: {}", id, err);
+        }
+
+        self.log_change(client_id, id, op, detail, list).await;
+    }
+
+    /// Appends to the log how list `id` became `list`, or that it was
+    /// deleted when `list` is `None`.
+    pub async fn log_change(
+        &self,
+        client_id: &str,
+        id: &str,
+        op: &str,
+        detail: &str,
+        list: Option<GroceryList>,
+    ) {
+        let mut logged = self.logged.lock().await;
+        let change = Change {
+            at: changelog::now_ms(),
+            client_id: client_id.to_string(),
+            list_id: id.to_string(),
+            op: op.to_string(),
+            detail: detail.to_string(),
+            edit: Edit::between(logged.get(id), list.as_ref()),
+        };
+        match list {
+            Some(list) => logged.insert(id.to_string(), list),
+            None => logged.remove(id),
+        };
+        if let Err(err) = self.store.append_change(&change).await {
+            eprintln!("Failed to log change to list {}: {}", id, err);
+        }
+    }
+
+    /// Writes the current trash to the store.
+    pub async fn persist_trash(&self) {
+        let trash = self.trash.lock().unwrap().clone();
+        if let Err(err) = self.store.save_trash(&trash).await {
+            eprintln!("Failed to persist trash: {}", err);
+        }
+    }
+
+    /// Writes the remembered categories to the store.
+    pub async fn persist_categories(&self) {
+        let categories = self.categories.lock().unwrap().clone();
+        if let Err(err) = self.store.save_categories(&categories).await {
+            eprintln!("Failed to persist categories: {}", err);
+        }
+    }
+
+    /// Writes the entries of `changed` to the store's index of item names.
+    pub async fn persist_names(&self, changed: &NameIndex) {
+        if let Err(err) = self.store.save_names(changed).await {
+            eprintln!("Failed to persist item names: {}", err);
+        }
+    }
+
+    /// Writes what was bought from each list to the store.
+    pub async fn persist_purchases(&self) {
+        let purchases = self.purchases.lock().unwrap().clone();
+        if let Err(err) = self.store.save_purchases(&purchases).await {
+            eprintln!("Failed to persist purchases: {}", err);
+        }
+    }
+
+    /// Writes the store layouts to the store.
+    pub async fn persist_store_layouts(&self) {
+        let layouts = self.store_layouts.lock().unwrap().clone();
+        if let Err(err) = self.store.save_store_layouts(&layouts).await {
+            eprintln!("Failed to persist store layouts: {}", err);
+        }
+    }
+
+    /// Writes the list templates to the store.
+    pub async fn persist_templates(&self) {
+        let templates = self.templates.lock().unwrap().clone();
+        if let Err(err) = self.store.save_templates(&templates).await {
+            eprintln!("Failed to persist list templates: {}", err);
+        }
     }
 }
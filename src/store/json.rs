@@ -0,0 +1,741 @@
+use super::{Lists, Store};
+use crate::categories::Categories;
+use crate::changelog::{Change, Changelog, Snapshot};
+use crate::events::Update;
+use crate::models::{GroceryList, ListTemplate, StoreLayout, TrashEntry};
+use crate::purchases::Purchases;
+use crate::state::{AppContext, AppState};
+use crate::suggestions::{NameIndex, NameStats};
+use futures::future::BoxFuture;
+use notify::{RecursiveMode, Result as NotifyResult, Watcher};
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, IgnoredAny},
+};
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Keeps all lists in a single pretty-printed JSON file.
+///
+/// The file always holds the complete map, so the store keeps its own copy of
+/// the last persisted lists and rewrites the whole file on every change.
+/// Writes go to a temporary file that replaces the original only once it is
+/// fully on disk, and the previous version is kept next to it as `.bak`.
+/// The trash is kept in a second file next to it, `.trash`, the remembered
+/// categories in `.categories`, the names offered as suggestions in
+/// `.names`, what was bought from each list in `.purchases`, the store
+/// layouts in `.stores`, the list templates in `.templates`, and the
+/// change log in two more: `.log` holds one change per line and is only
+/// ever appended to, `.snapshot` holds the snapshot compaction leaves
+/// behind. A changed name is appended to `.names` as a line of its own,
+/// and the file is rewritten with one line per name once it holds twice as
+/// many lines as names.
+pub struct JsonStore {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    path: PathBuf,
+    debounce: Duration,
+    persisted: Mutex<Persisted>,
+    /// Held while `.names` is written, so appends and rewrites do not mix.
+    names: tokio::sync::Mutex<StoredNames>,
+}
+
+/// What yala last wrote to (or read from) the file. The exact contents are
+/// kept so the watcher can tell our own writes apart from external edits.
+#[derive(Default)]
+struct Persisted {
+    lists: Lists,
+    json: String,
+}
+
+/// The name index as `.names` holds it, and how many lines that takes.
+#[derive(Default)]
+struct StoredNames {
+    names: NameIndex,
+    lines: usize,
+}
+
+impl JsonStore {
+    /// `debounce` is how long the watcher waits for an external edit to
+    /// settle before reading the file.
+    pub fn new(path: impl Into<PathBuf>, debounce: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                path: path.into(),
+                debounce,
+                persisted: Mutex::new(Persisted::default()),
+                names: tokio::sync::Mutex::default(),
+            }),
+        }
+    }
+}
+
+impl Inner {
+    async fn write(&self, persisted: &mut Persisted) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(&persisted.lists)?;
+        if fs::try_exists(&self.path).await? {
+            fs::copy(&self.path, self.sibling("bak")).await?;
+        }
+        replace(&self.path, &json).await?;
+        persisted.json = json;
+        Ok(())
+    }
+
+    fn parse(&self, json: &str) -> io::Result<Lists> {
+        serde_json::from_str(json).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} could not be parsed ({})", self.path.display(), err),
+            )
+        })
+    }
+
+    /// Re-reads the file after an external edit and merges it into `state`.
+    /// Returns the ids of the lists that changed, which is none when the
+    /// event came from our own write or the edited file is not valid.
+    async fn reload(&self, state: &AppState) -> Vec<String> {
+        let mut persisted = self.persisted.lock().await;
+
+        let json = match fs::read_to_string(&self.path).await {
+            Ok(json) => json,
+            Err(_) => return vec![],
+        };
+        if json == persisted.json {
+            return vec![];
+        }
+
+        let mut edited = match self.parse(&json) {
+            Ok(lists) => lists,
+            Err(err) => {
+                eprintln!("Ignoring external edit: {}", err);
+                return vec![];
+            }
+        };
+
+        let mut missing_ids = false;
+        for list in edited.values_mut() {
+            missing_ids |= list.assign_missing_ids();
+        }
+
+        let changed = merge(&mut state.write().unwrap(), &persisted.lists, &edited);
+        *persisted = Persisted {
+            lists: edited,
+            json,
+        };
+
+        // Items added by hand get their ids written back right away, so the
+        // ids clients see survive a restart.
+        if missing_ids && let Err(err) = self.write(&mut persisted).await {
+            eprintln!(
+                "Failed to write item ids to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+        changed
+    }
+
+    /// Path next to the store file with `suffix` appended, e.g. `lists.json.bak`.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(".");
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    fn dir(&self) -> &Path {
+        dir_of(&self.path)
+    }
+}
+
+fn dir_of(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Replaces the file at `path` with `contents` through a temporary file, so
+/// a crash leaves either the old or the new version behind.
+async fn replace(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp = OsString::from(path.as_os_str());
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = fs::File::create(&tmp).await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&tmp, path).await?;
+    sync_dir(dir_of(path)).await
+}
+
+/// Applies the lists that changed between `base` and `edited` to `state`
+/// and returns their ids. Lists the edit did not touch keep their in-memory
+/// version, so a change that is still on its way to disk is not lost.
+fn merge(state: &mut Lists, base: &Lists, edited: &Lists) -> Vec<String> {
+    let mut changed = vec![];
+    for (id, list) in edited {
+        if base.get(id) != Some(list) {
+            state.insert(id.clone(), list.clone());
+            changed.push(id.clone());
+        }
+    }
+    for id in base.keys() {
+        if !edited.contains_key(id) {
+            state.remove(id);
+            changed.push(id.clone());
+        }
+    }
+    changed
+}
+
+/// Reads a JSON file written by `replace`; a missing file reads as empty.
+async fn read_json<T: DeserializeOwned + Default>(path: &Path) -> io::Result<T> {
+    match fs::read_to_string(path).await {
+        Ok(json) => serde_json::from_str(&json).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} could not be parsed ({})", path.display(), err),
+            )
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// What `read_lines_where` does with a line.
+enum Line {
+    Keep,
+    Skip,
+    Stop,
+}
+
+/// Reads a file with one JSON value per line. A missing file is empty, and
+/// a last line cut short by a crash is skipped. Each line is first read as
+/// an `H`, usually a couple of its fields, and `pick` decides whether it is
+/// worth parsing whole.
+async fn read_lines_where<H, T>(path: &Path, mut pick: impl FnMut(H) -> Line) -> io::Result<Vec<T>>
+where
+    H: DeserializeOwned,
+    T: DeserializeOwned,
+{
+    let text = match fs::read_to_string(path).await {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+    let lines: Vec<&str> = text.lines().filter(|line| !line.is_empty()).collect();
+    let mut values = vec![];
+    for (n, line) in lines.iter().enumerate() {
+        let parsed = match serde_json::from_str(line) {
+            Ok(header) => match pick(header) {
+                Line::Keep => serde_json::from_str(line),
+                Line::Skip => continue,
+                Line::Stop => break,
+            },
+            Err(err) => Err(err),
+        };
+        match parsed {
+            Ok(value) => values.push(value),
+            Err(_) if n + 1 == lines.len() && !text.ends_with('\n') => break,
+            Err(err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} line {} could not be parsed ({})",
+                        path.display(),
+                        n + 1,
+                        err
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Reads every line of a file with one JSON value per line.
+async fn read_lines<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    read_lines_where(path, |_: IgnoredAny| Line::Keep).await
+}
+
+/// A line of `.names`: one name and its stats, as they were last changed.
+#[derive(Serialize, Deserialize)]
+struct NameLine {
+    key: String,
+    #[serde(flatten)]
+    stats: NameStats,
+}
+
+fn name_lines(names: &NameIndex) -> Vec<NameLine> {
+    names
+        .iter()
+        .map(|(key, stats)| NameLine {
+            key: key.clone(),
+            stats: stats.clone(),
+        })
+        .collect()
+}
+
+/// Rewrites `.names` with one line per name.
+async fn compact_names(path: &Path, stored: &mut StoredNames) -> io::Result<()> {
+    replace(path, &to_lines(&name_lines(&stored.names))?).await?;
+    stored.lines = stored.names.iter().count();
+    Ok(())
+}
+
+fn to_lines<T: Serialize>(values: &[T]) -> io::Result<String> {
+    let mut text = String::new();
+    for value in values {
+        text.push_str(&serde_json::to_string(value)?);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Makes a completed rename durable by flushing the directory entry.
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir).await?.sync_all().await
+}
+
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl Store for JsonStore {
+    fn load(&self) -> BoxFuture<'_, io::Result<Lists>> {
+        Box::pin(async move {
+            let inner = &self.inner;
+            let json = match fs::read_to_string(&inner.path).await {
+                Ok(json) => json,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Lists::new()),
+                Err(err) => return Err(err),
+            };
+            let lists = inner.parse(&json).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!(
+                        "{}; fix it or restore {} before starting",
+                        err,
+                        inner.sibling("bak").display()
+                    ),
+                )
+            })?;
+
+            *inner.persisted.lock().await = Persisted {
+                lists: lists.clone(),
+                json,
+            };
+            Ok(lists)
+        })
+    }
+
+    fn save_list<'a>(
+        &'a self,
+        id: &'a str,
+        list: &'a GroceryList,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut persisted = self.inner.persisted.lock().await;
+            persisted.lists.insert(id.to_string(), list.clone());
+            self.inner.write(&mut persisted).await
+        })
+    }
+
+    fn delete_list<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut persisted = self.inner.persisted.lock().await;
+            persisted.lists.remove(id);
+            self.inner.write(&mut persisted).await
+        })
+    }
+
+    fn load_trash(&self) -> BoxFuture<'_, io::Result<Vec<TrashEntry>>> {
+        Box::pin(async move { read_json(&self.inner.sibling("trash")).await })
+    }
+
+    fn save_trash<'a>(&'a self, trash: &'a [TrashEntry]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let json = serde_json::to_string_pretty(trash)?;
+            replace(&self.inner.sibling("trash"), &json).await
+        })
+    }
+
+    fn load_categories(&self) -> BoxFuture<'_, io::Result<Categories>> {
+        Box::pin(async move { read_json(&self.inner.sibling("categories")).await })
+    }
+
+    fn save_categories<'a>(&'a self, categories: &'a Categories) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let json = serde_json::to_string_pretty(categories)?;
+            replace(&self.inner.sibling("categories"), &json).await
+        })
+    }
+
+    /// Later lines replace earlier ones for the same name.
+    fn load_names(&self) -> BoxFuture<'_, io::Result<NameIndex>> {
+        Box::pin(async move {
+            let path = self.inner.sibling("names");
+            let lines: Vec<NameLine> = read_lines(&path).await?;
+            let mut stored = self.inner.names.lock().await;
+            stored.lines = lines.len();
+            stored.names = NameIndex::default();
+            for line in lines {
+                stored.names.insert(line.key, line.stats);
+            }
+            if stored.lines > stored.names.iter().count() {
+                compact_names(&path, &mut stored).await?;
+            }
+            Ok(stored.names.clone())
+        })
+    }
+
+    fn save_names<'a>(&'a self, names: &'a NameIndex) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let path = self.inner.sibling("names");
+            let mut stored = self.inner.names.lock().await;
+            for (key, stats) in names.iter() {
+                stored.names.insert(key.clone(), stats.clone());
+            }
+            if stored.lines + names.iter().count() >= 2 * stored.names.iter().count() {
+                return compact_names(&path, &mut stored).await;
+            }
+
+            let text = to_lines(&name_lines(names))?;
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await?;
+            file.write_all(text.as_bytes()).await?;
+            file.sync_data().await?;
+            stored.lines += names.iter().count();
+            Ok(())
+        })
+    }
+
+    fn load_purchases(&self) -> BoxFuture<'_, io::Result<Purchases>> {
+        Box::pin(async move { read_json(&self.inner.sibling("purchases")).await })
+    }
+
+    fn save_purchases<'a>(&'a self, purchases: &'a Purchases) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let json = serde_json::to_string_pretty(purchases)?;
+            replace(&self.inner.sibling("purchases"), &json).await
+        })
+    }
+
+    fn load_store_layouts(&self) -> BoxFuture<'_, io::Result<Vec<StoreLayout>>> {
+        Box::pin(async move { read_json(&self.inner.sibling("stores")).await })
+    }
+
+    fn save_store_layouts<'a>(
+        &'a self,
+        layouts: &'a [StoreLayout],
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let json = serde_json::to_string_pretty(layouts)?;
+            replace(&self.inner.sibling("stores"), &json).await
+        })
+    }
+
+    fn load_templates(&self) -> BoxFuture<'_, io::Result<Vec<ListTemplate>>> {
+        Box::pin(async move { read_json(&self.inner.sibling("templates")).await })
+    }
+
+    fn save_templates<'a>(
+        &'a self,
+        templates: &'a [ListTemplate],
+    ) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let json = serde_json::to_string_pretty(templates)?;
+            replace(&self.inner.sibling("templates"), &json).await
+        })
+    }
+
+    fn append_change<'a>(&'a self, change: &'a Change) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_string(change)?;
+            line.push('\n');
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.inner.sibling("log"))
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.sync_data().await
+        })
+    }
+
+    fn read_snapshot(&self) -> BoxFuture<'_, io::Result<Option<Snapshot>>> {
+        Box::pin(async move { read_json(&self.inner.sibling("snapshot")).await })
+    }
+
+    fn read_changes<'a>(
+        &'a self,
+        list_id: Option<&'a str>,
+        until: u64,
+    ) -> BoxFuture<'a, io::Result<Vec<Change>>> {
+        /// The fields of a logged change that decide whether it is read.
+        #[derive(Deserialize)]
+        struct Header {
+            at: u64,
+            list_id: String,
+        }
+
+        Box::pin(async move {
+            // Changes are appended in order, so the first one after `until`
+            // ends the search.
+            read_lines_where(&self.inner.sibling("log"), |change: Header| {
+                if change.at > until {
+                    Line::Stop
+                } else if list_id.is_some_and(|id| id != change.list_id) {
+                    Line::Skip
+                } else {
+                    Line::Keep
+                }
+            })
+            .await
+        })
+    }
+
+    fn write_log<'a>(&'a self, log: &'a Changelog) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            // The snapshot first: if the second write does not happen, the
+            // changes it covers are still in the log and replaying them
+            // again on top of the snapshot is harmless.
+            let snapshot = serde_json::to_string(&log.snapshot)?;
+            replace(&self.inner.sibling("snapshot"), &snapshot).await?;
+            replace(&self.inner.sibling("log"), &to_lines(&log.changes)?).await
+        })
+    }
+
+    fn watch(&self, ctx: AppContext) -> BoxFuture<'static, ()> {
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let (notify_tx, mut notify_rx) = tokio::sync::mpsc::channel(100);
+
+            let file_name = inner.path.file_name().map(OsString::from);
+            let mut watcher =
+                notify::recommended_watcher(move |res: NotifyResult<notify::Event>| {
+                    // The file is replaced by a rename on every write, so the
+                    // directory is watched rather than the file's inode.
+                    if let Ok(event) = res
+                        && (event.kind.is_modify() || event.kind.is_create())
+                        && event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == file_name.as_deref())
+                    {
+                        let _ = notify_tx.blocking_send(());
+                    }
+                })
+                .unwrap();
+
+            let _ = watcher.watch(inner.dir(), RecursiveMode::NonRecursive);
+
+            while notify_rx.recv().await.is_some() {
+                // Editors often save in several steps; wait for them to settle
+                // and handle the burst as a single change.
+                tokio::time::sleep(inner.debounce).await;
+                while notify_rx.try_recv().is_ok() {}
+
+                let changed = inner.reload(&ctx.state).await;
+                if changed.is_empty() {
+                    continue;
+                }
+                for id in changed {
+                    let list = ctx.state.read().unwrap().get(&id).cloned();
+                    ctx.log_change("", &id, "external_edit", "", list).await;
+                }
+                ctx.broadcast("", Update::Reload { list_id: None });
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changelog::Edit;
+    use crate::{config::Config, state::AppContext};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yala-json-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn list(name: &str) -> GroceryList {
+        GroceryList {
+            name: name.to_string(),
+            items: vec![],
+            show_completed: true,
+            store: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_to_load_a_damaged_file_and_leaves_it_alone() {
+        let dir = temp_dir("damaged");
+        let path = dir.join("lists.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        std::fs::write(dir.join("lists.json.bak"), "{}").unwrap();
+
+        let store = Arc::new(JsonStore::new(&path, Duration::ZERO));
+        assert!(
+            AppContext::with_store(store, Config::default())
+                .await
+                .is_err()
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("lists.json.bak")).unwrap(),
+            "{}"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replaces_the_file_through_a_temporary_one_and_keeps_a_backup() {
+        let dir = temp_dir("replace");
+        let path = dir.join("lists.json");
+        let store = JsonStore::new(&path, Duration::ZERO);
+        store.load().await.unwrap();
+
+        store.save_list("a", &list("First")).await.unwrap();
+        let first = std::fs::read_to_string(&path).unwrap();
+        store.save_list("a", &list("Second")).await.unwrap();
+
+        let saved: Lists = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["a"].name, "Second");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("lists.json.bak")).unwrap(),
+            first
+        );
+        assert!(!dir.join("lists.json.tmp").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_skips_our_own_writes_and_merges_external_edits() {
+        let dir = temp_dir("reload");
+        let path = dir.join("lists.json");
+        let store = JsonStore::new(&path, Duration::ZERO);
+        store.load().await.unwrap();
+        store.save_list("a", &list("First")).await.unwrap();
+        store.save_list("b", &list("Other")).await.unwrap();
+
+        let state = AppState::default();
+        state.write().unwrap().insert("a".into(), list("First"));
+        state.write().unwrap().insert("b".into(), list("Unsaved"));
+        assert!(store.inner.reload(&state).await.is_empty());
+
+        let mut edited: Lists =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        edited.insert("a".into(), list("Edited"));
+        std::fs::write(&path, serde_json::to_string(&edited).unwrap()).unwrap();
+        assert_eq!(store.inner.reload(&state).await, ["a"]);
+
+        let state = state.read().unwrap();
+        assert_eq!(state["a"].name, "Edited");
+        assert_eq!(state["b"].name, "Unsaved");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_writes_assigned_ids_back() {
+        let dir = temp_dir("ids");
+        let path = dir.join("lists.json");
+        let store = JsonStore::new(&path, Duration::ZERO);
+        store.load().await.unwrap();
+
+        std::fs::write(
+            &path,
+            r#"{"a": {"name": "A", "items": [{"name": "milk", "completed": false}], "show_completed": true}}"#,
+        )
+        .unwrap();
+        let state = AppState::default();
+        assert_eq!(store.inner.reload(&state).await, ["a"]);
+
+        let id = state.read().unwrap()["a"].items[0].id.clone();
+        assert!(!id.is_empty());
+        let saved: Lists = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["a"].items[0].id, id);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_only_the_changes_asked_for() {
+        let dir = temp_dir("log");
+        let store = JsonStore::new(dir.join("lists.json"), Duration::ZERO);
+        for (at, list_id) in [(10, "a"), (20, "b"), (30, "a")] {
+            let change = Change {
+                at,
+                client_id: String::new(),
+                list_id: list_id.to_string(),
+                op: "delete_list".to_string(),
+                detail: String::new(),
+                edit: Edit::Deleted,
+            };
+            store.append_change(&change).await.unwrap();
+        }
+
+        let at = |changes: Vec<Change>| changes.iter().map(|c| c.at).collect::<Vec<_>>();
+        assert_eq!(at(store.read_changes(Some("a"), 25).await.unwrap()), [10]);
+        assert_eq!(
+            at(store.read_changes(Some("a"), u64::MAX).await.unwrap()),
+            [10, 30]
+        );
+        assert_eq!(at(store.read_changes(None, 20).await.unwrap()), [10, 20]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rewrites_the_names_once_stale_lines_pile_up() {
+        let dir = temp_dir("names");
+        let store = JsonStore::new(dir.join("lists.json"), Duration::ZERO);
+        let mut names = store.load_names().await.unwrap();
+        for (n, name) in ["milk", "eggs", "milk", "milk", "milk", "milk"]
+            .into_iter()
+            .enumerate()
+        {
+            let (key, stats) = names.record("groceries", name, n as u64);
+            let mut changed = NameIndex::default();
+            changed.insert(key, stats);
+            store.save_names(&changed).await.unwrap();
+
+            let text = std::fs::read_to_string(dir.join("lists.json.names")).unwrap();
+            assert!(text.lines().count() < 2 * names.iter().count());
+        }
+
+        let reopened = JsonStore::new(dir.join("lists.json"), Duration::ZERO);
+        let loaded = reopened.load_names().await.unwrap();
+        let counts: Vec<(&str, u32)> = loaded
+            .iter()
+            .map(|(key, stats)| (key.as_str(), stats.count))
+            .collect();
+        assert_eq!(counts, [("eggs", 1), ("milk", 5)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+mod json;
+mod sqlite;
+
+pub use json::JsonStore;
+pub use sqlite::SqliteStore;
+
+use crate::categories::Categories;
+use crate::changelog::{Change, Changelog, Snapshot};
+use crate::config::Config;
+use crate::models::{GroceryList, ListTemplate, StoreLayout, TrashEntry};
+use crate::purchases::Purchases;
+use crate::state::AppContext;
+use crate::suggestions::NameIndex;
+use futures::future::BoxFuture;
+use std::{collections::HashMap, io, sync::Arc};
+
+pub type Lists = HashMap<String, GroceryList>;
+
+/// Persistence backend for the lists held in `AppContext`.
+///
+/// Handlers mutate the in-memory state and then hand the affected list to the
+/// store, so a backend only ever has to deal with one list at a time.
+pub trait Store: Send + Sync {
+    /// Reads every list from the backend.
+    fn load(&self) -> BoxFuture<'_, io::Result<Lists>>;
+
+    /// Inserts or replaces the list stored under `id`.
+    fn save_list<'a>(&'a self, id: &'a str, list: &'a GroceryList)
+    -> BoxFuture<'a, io::Result<()>>;
+
+    /// Removes the list stored under `id`, if any.
+    fn delete_list<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the deleted lists and items that have not been purged yet.
+    fn load_trash(&self) -> BoxFuture<'_, io::Result<Vec<TrashEntry>>>;
+
+    /// Replaces the stored trash with `trash`.
+    fn save_trash<'a>(&'a self, trash: &'a [TrashEntry]) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the remembered category of each item name.
+    fn load_categories(&self) -> BoxFuture<'_, io::Result<Categories>>;
+
+    /// Replaces the remembered categories with `categories`.
+    fn save_categories<'a>(&'a self, categories: &'a Categories) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the index of item names offered as suggestions.
+    fn load_names(&self) -> BoxFuture<'_, io::Result<NameIndex>>;
+
+    /// Adds the entries of `names` to the stored index, replacing what was
+    /// stored for the same names. Other names are left as they are.
+    fn save_names<'a>(&'a self, names: &'a NameIndex) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads what was bought from each list.
+    fn load_purchases(&self) -> BoxFuture<'_, io::Result<Purchases>>;
+
+    /// Replaces the stored purchases with `purchases`.
+    fn save_purchases<'a>(&'a self, purchases: &'a Purchases) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the store layouts, in the order they were created.
+    fn load_store_layouts(&self) -> BoxFuture<'_, io::Result<Vec<StoreLayout>>>;
+
+    /// Replaces the stored layouts with `layouts`.
+    fn save_store_layouts<'a>(
+        &'a self,
+        layouts: &'a [StoreLayout],
+    ) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the list templates, in the order they were saved.
+    fn load_templates(&self) -> BoxFuture<'_, io::Result<Vec<ListTemplate>>>;
+
+    /// Replaces the stored templates with `templates`.
+    fn save_templates<'a>(&'a self, templates: &'a [ListTemplate])
+    -> BoxFuture<'a, io::Result<()>>;
+
+    /// Appends `change` to the change log.
+    fn append_change<'a>(&'a self, change: &'a Change) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Reads the snapshot left by the last compaction, if any.
+    fn read_snapshot(&self) -> BoxFuture<'_, io::Result<Option<Snapshot>>>;
+
+    /// Reads the logged changes made up to `until`, oldest first. With
+    /// `list_id`, only the changes to that list are read.
+    fn read_changes<'a>(
+        &'a self,
+        list_id: Option<&'a str>,
+        until: u64,
+    ) -> BoxFuture<'a, io::Result<Vec<Change>>>;
+
+    /// Reads what it takes to rebuild the lists as they were at `at`. With
+    /// `list_id`, only that list comes out right.
+    fn read_log<'a>(
+        &'a self,
+        list_id: Option<&'a str>,
+        at: u64,
+    ) -> BoxFuture<'a, io::Result<Changelog>> {
+        Box::pin(async move {
+            Ok(Changelog {
+                snapshot: self.read_snapshot().await?,
+                changes: self.read_changes(list_id, at).await?,
+            })
+        })
+    }
+
+    /// Replaces the snapshot and all changes, e.g. after compaction.
+    fn write_log<'a>(&'a self, log: &'a Changelog) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Watches the backend for changes made outside of yala, applies them to
+    /// the state in `ctx` and notifies connected clients. Backends that cannot
+    /// be edited externally keep the default, which returns immediately.
+    fn watch(&self, _ctx: AppContext) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
+}
+
+/// Opens the backend matching the extension of the data path: `.db`, `.sqlite`
+/// and `.sqlite3` select SQLite, anything else is treated as a JSON file.
+///
+/// A new SQLite database imports the JSON file with the same stem, so
+/// switching from `lists.json` to `lists.db` keeps existing lists.
+pub fn open(config: &Config) -> io::Result<Arc<dyn Store>> {
+    let path = &config.data;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("db" | "sqlite" | "sqlite3") => Ok(Arc::new(SqliteStore::open(
+            path,
+            path.with_extension("json"),
+        )?)),
+        _ => Ok(Arc::new(JsonStore::new(path, config.watch_debounce))),
+    }
+}
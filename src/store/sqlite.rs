@@ -0,0 +1,743 @@
+use super::{Lists, Store};
+use crate::categories::Categories;
+use crate::changelog::{Change, Changelog, Snapshot};
+use crate::models::{GroceryList, Item, ListTemplate, StoreLayout, TrashEntry};
+use crate::purchases::Purchases;
+use crate::suggestions::{NameIndex, NameStats};
+use futures::future::BoxFuture;
+use rusqlite::{Connection, OptionalExtension, Row, Transaction, params, types::Type};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// of them a database has already seen.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE lists (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        show_completed INTEGER NOT NULL
+    );
+    CREATE TABLE items (
+        list_id TEXT NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        PRIMARY KEY (list_id, position)
+    );
+    ",
+    // Items are keyed by a stable id instead of their position.
+    "
+    CREATE TABLE items_v2 (
+        id TEXT PRIMARY KEY,
+        list_id TEXT NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        completed INTEGER NOT NULL
+    );
+    INSERT INTO items_v2 (id, list_id, position, name, completed)
+        SELECT lower(hex(randomblob(16))), list_id, position, name, completed FROM items;
+    DROP TABLE items;
+    ALTER TABLE items_v2 RENAME TO items;
+    CREATE INDEX items_list ON items (list_id, position);
+    ",
+    // Deleted lists and items; `content` is the JSON of `Trashed`.
+    "
+    CREATE TABLE trash (
+        id TEXT PRIMARY KEY,
+        deleted_at INTEGER NOT NULL,
+        list_id TEXT NOT NULL,
+        content TEXT NOT NULL
+    );
+    ",
+    // The change log; `edit` and `lists` hold JSON.
+    "
+    CREATE TABLE changes (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        at INTEGER NOT NULL,
+        client_id TEXT NOT NULL,
+        list_id TEXT NOT NULL,
+        op TEXT NOT NULL,
+        detail TEXT NOT NULL,
+        edit TEXT NOT NULL
+    );
+    CREATE INDEX changes_list ON changes (list_id, at);
+    CREATE TABLE snapshots (
+        at INTEGER PRIMARY KEY,
+        lists TEXT NOT NULL
+    );
+    ",
+    // Optional quantity and unit per item.
+    "
+    ALTER TABLE items ADD COLUMN quantity REAL;
+    ALTER TABLE items ADD COLUMN unit TEXT;
+    ",
+    "ALTER TABLE items ADD COLUMN notes TEXT;",
+    // Item categories, and the category each item name was last filed under.
+    "
+    ALTER TABLE items ADD COLUMN category TEXT;
+    CREATE TABLE categories (
+        name TEXT PRIMARY KEY,
+        category TEXT NOT NULL
+    );
+    ",
+    // Store layouts, and the store each list is shopped at; `categories`
+    // holds a JSON array in walk order.
+    "
+    ALTER TABLE lists ADD COLUMN store TEXT;
+    CREATE TABLE store_layouts (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        categories TEXT NOT NULL
+    );
+    ",
+    // Item names offered as suggestions; `lists` holds a JSON object of
+    // how often the name was added to each list.
+    "
+    CREATE TABLE names (
+        key TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        last_added INTEGER NOT NULL,
+        lists TEXT NOT NULL
+    );
+    ",
+    // What was bought from each list; `purchases` holds a JSON array, most
+    // recent first. Not tied to `lists`, so it outlives a deleted list.
+    "
+    CREATE TABLE purchases (
+        list_id TEXT PRIMARY KEY,
+        purchases TEXT NOT NULL
+    );
+    ",
+    // List templates; `items` holds a JSON array of the items.
+    "
+    CREATE TABLE templates (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        items TEXT NOT NULL
+    );
+    ",
+];
+
+/// Keeps lists and their items in an SQLite database.
+///
+/// Unlike `JsonStore`, a change only touches the rows that differ from the
+/// last persisted version of the list.
+pub struct SqliteStore {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    conn: Connection,
+    lists: Lists,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path`. A freshly created database
+    /// is seeded once from the JSON file at `import`, if it exists.
+    pub fn open(path: impl AsRef<Path>, import: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut conn = Connection::open(path).map_err(to_io)?;
+        conn.pragma_update(None, "foreign_keys", true)
+            .map_err(to_io)?;
+
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(to_io)?;
+
+        if (version as usize) < MIGRATIONS.len() {
+            let tx = conn.transaction().map_err(to_io)?;
+            for migration in &MIGRATIONS[version as usize..] {
+                tx.execute_batch(migration).map_err(to_io)?;
+            }
+            if version == 0 {
+                import_json(&tx, &import.into())?;
+            }
+            tx.pragma_update(None, "user_version", MIGRATIONS.len())
+                .map_err(to_io)?;
+            tx.commit().map_err(to_io)?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                conn,
+                lists: Lists::new(),
+            })),
+        })
+    }
+
+    async fn run<T, F>(&self, f: F) -> io::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Inner) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(&mut inner.lock().unwrap()))
+            .await
+            .map_err(io::Error::other)?
+            .map_err(to_io)
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&self) -> BoxFuture<'_, io::Result<Lists>> {
+        Box::pin(self.run(|inner| {
+            let lists = read_lists(&inner.conn)?;
+            inner.lists = lists.clone();
+            Ok(lists)
+        }))
+    }
+
+    fn save_list<'a>(
+        &'a self,
+        id: &'a str,
+        list: &'a GroceryList,
+    ) -> BoxFuture<'a, io::Result<()>> {
+        let id = id.to_string();
+        let list = list.clone();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            write_list_diff(&tx, &id, inner.lists.get(&id), &list)?;
+            tx.commit()?;
+            inner.lists.insert(id, list);
+            Ok(())
+        }))
+    }
+
+    fn delete_list<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        let id = id.to_string();
+        Box::pin(self.run(move |inner| {
+            inner
+                .conn
+                .execute("DELETE FROM lists WHERE id = ?1", params![id])?;
+            inner.lists.remove(&id);
+            Ok(())
+        }))
+    }
+
+    fn load_trash(&self) -> BoxFuture<'_, io::Result<Vec<TrashEntry>>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner.conn.prepare(
+                "SELECT id, deleted_at, list_id, content FROM trash ORDER BY deleted_at",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(TrashEntry {
+                    id: row.get(0)?,
+                    deleted_at: row.get(1)?,
+                    list_id: row.get(2)?,
+                    content: from_json(row, 3)?,
+                })
+            })?;
+            rows.collect()
+        }))
+    }
+
+    fn save_trash<'a>(&'a self, trash: &'a [TrashEntry]) -> BoxFuture<'a, io::Result<()>> {
+        let trash = trash.to_vec();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM trash", [])?;
+            for entry in &trash {
+                tx.execute(
+                    "INSERT INTO trash (id, deleted_at, list_id, content) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        entry.id,
+                        entry.deleted_at,
+                        entry.list_id,
+                        to_json(&entry.content)?
+                    ],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn load_categories(&self) -> BoxFuture<'_, io::Result<Categories>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner
+                .conn
+                .prepare("SELECT name, category FROM categories")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?;
+            let mut categories = Categories::default();
+            for row in rows {
+                let (name, category): (String, String) = row?;
+                categories.remember(&name, Some(&category));
+            }
+            Ok(categories)
+        }))
+    }
+
+    fn save_categories<'a>(&'a self, categories: &'a Categories) -> BoxFuture<'a, io::Result<()>> {
+        let categories = categories.clone();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM categories", [])?;
+            for (name, category) in categories.iter() {
+                tx.execute(
+                    "INSERT INTO categories (name, category) VALUES (?1, ?2)",
+                    params![name, category],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn load_names(&self) -> BoxFuture<'_, io::Result<NameIndex>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner
+                .conn
+                .prepare("SELECT key, name, count, last_added, lists FROM names")?;
+            let rows = stmt.query_map([], |row| {
+                let stats = NameStats {
+                    name: row.get(1)?,
+                    count: row.get(2)?,
+                    last_added: row.get(3)?,
+                    lists: from_json(row, 4)?,
+                };
+                Ok((row.get(0)?, stats))
+            })?;
+            let mut names = NameIndex::default();
+            for row in rows {
+                let (key, stats) = row?;
+                names.insert(key, stats);
+            }
+            Ok(names)
+        }))
+    }
+
+    fn save_names<'a>(&'a self, names: &'a NameIndex) -> BoxFuture<'a, io::Result<()>> {
+        let names = names.clone();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            for (key, stats) in names.iter() {
+                tx.execute(
+                    "INSERT INTO names (key, name, count, last_added, lists)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(key) DO UPDATE SET name = excluded.name,
+                         count = excluded.count, last_added = excluded.last_added,
+                         lists = excluded.lists",
+                    params![
+                        key,
+                        stats.name,
+                        stats.count,
+                        stats.last_added,
+                        to_json(&stats.lists)?
+                    ],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn load_purchases(&self) -> BoxFuture<'_, io::Result<Purchases>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner
+                .conn
+                .prepare("SELECT list_id, purchases FROM purchases")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, from_json(row, 1)?)))?;
+            let mut purchases = Purchases::default();
+            for row in rows {
+                let (list_id, list_purchases) = row?;
+                purchases.insert(list_id, list_purchases);
+            }
+            Ok(purchases)
+        }))
+    }
+
+    fn save_purchases<'a>(&'a self, purchases: &'a Purchases) -> BoxFuture<'a, io::Result<()>> {
+        let purchases = purchases.clone();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM purchases", [])?;
+            for (list_id, list_purchases) in purchases.iter() {
+                tx.execute(
+                    "INSERT INTO purchases (list_id, purchases) VALUES (?1, ?2)",
+                    params![list_id, to_json(list_purchases)?],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn load_store_layouts(&self) -> BoxFuture<'_, io::Result<Vec<StoreLayout>>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner
+                .conn
+                .prepare("SELECT id, name, categories FROM store_layouts ORDER BY position")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(StoreLayout {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    categories: from_json(row, 2)?,
+                })
+            })?;
+            rows.collect()
+        }))
+    }
+
+    fn save_store_layouts<'a>(
+        &'a self,
+        layouts: &'a [StoreLayout],
+    ) -> BoxFuture<'a, io::Result<()>> {
+        let layouts = layouts.to_vec();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM store_layouts", [])?;
+            for (position, layout) in layouts.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO store_layouts (id, position, name, categories)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        layout.id,
+                        position,
+                        layout.name,
+                        to_json(&layout.categories)?
+                    ],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn load_templates(&self) -> BoxFuture<'_, io::Result<Vec<ListTemplate>>> {
+        Box::pin(self.run(|inner| {
+            let mut stmt = inner
+                .conn
+                .prepare("SELECT id, name, items FROM templates ORDER BY position")?;
+            let rows = stmt.query_map([], |row| {
+                Ok(ListTemplate {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    items: from_json(row, 2)?,
+                })
+            })?;
+            rows.collect()
+        }))
+    }
+
+    fn save_templates<'a>(
+        &'a self,
+        templates: &'a [ListTemplate],
+    ) -> BoxFuture<'a, io::Result<()>> {
+        let templates = templates.to_vec();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM templates", [])?;
+            for (position, template) in templates.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO templates (id, position, name, items) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        template.id,
+                        position,
+                        template.name,
+                        to_json(&template.items)?
+                    ],
+                )?;
+            }
+            tx.commit()
+        }))
+    }
+
+    fn append_change<'a>(&'a self, change: &'a Change) -> BoxFuture<'a, io::Result<()>> {
+        let change = change.clone();
+        Box::pin(self.run(move |inner| insert_change(&inner.conn, &change)))
+    }
+
+    fn read_snapshot(&self) -> BoxFuture<'_, io::Result<Option<Snapshot>>> {
+        Box::pin(self.run(|inner| {
+            inner
+                .conn
+                .query_row(
+                    "SELECT at, lists FROM snapshots ORDER BY at DESC LIMIT 1",
+                    [],
+                    |row| {
+                        Ok(Snapshot {
+                            at: row.get(0)?,
+                            lists: from_json(row, 1)?,
+                        })
+                    },
+                )
+                .optional()
+        }))
+    }
+
+    fn read_changes<'a>(
+        &'a self,
+        list_id: Option<&'a str>,
+        until: u64,
+    ) -> BoxFuture<'a, io::Result<Vec<Change>>> {
+        let list_id = list_id.map(str::to_string);
+        // SQLite integers are signed; `u64::MAX` stands for "no limit".
+        let until = i64::try_from(until).unwrap_or(i64::MAX);
+        Box::pin(self.run(move |inner| {
+            let mut stmt = inner.conn.prepare(
+                "SELECT at, client_id, list_id, op, detail, edit FROM changes
+                 WHERE at <= ?1 AND (?2 IS NULL OR list_id = ?2) ORDER BY seq",
+            )?;
+            stmt.query_map(params![until, list_id], |row| {
+                Ok(Change {
+                    at: row.get(0)?,
+                    client_id: row.get(1)?,
+                    list_id: row.get(2)?,
+                    op: row.get(3)?,
+                    detail: row.get(4)?,
+                    edit: from_json(row, 5)?,
+                })
+            })?
+            .collect()
+        }))
+    }
+
+    fn write_log<'a>(&'a self, log: &'a Changelog) -> BoxFuture<'a, io::Result<()>> {
+        let snapshot = log.snapshot.clone();
+        let changes = log.changes.clone();
+        Box::pin(self.run(move |inner| {
+            let tx = inner.conn.transaction()?;
+            tx.execute("DELETE FROM snapshots", [])?;
+            tx.execute("DELETE FROM changes", [])?;
+            if let Some(snapshot) = &snapshot {
+                tx.execute(
+                    "INSERT INTO snapshots (at, lists) VALUES (?1, ?2)",
+                    params![snapshot.at, to_json(&snapshot.lists)?],
+                )?;
+            }
+            for change in &changes {
+                insert_change(&tx, change)?;
+            }
+            tx.commit()
+        }))
+    }
+}
+
+fn insert_change(conn: &Connection, change: &Change) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO changes (at, client_id, list_id, op, detail, edit)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            change.at,
+            change.client_id,
+            change.list_id,
+            change.op,
+            change.detail,
+            to_json(&change.edit)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+/// Parses the JSON text in column `index`.
+fn from_json<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_str(&text)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+}
+
+fn read_lists(conn: &Connection) -> rusqlite::Result<Lists> {
+    let mut lists = Lists::new();
+
+    let mut stmt = conn.prepare("SELECT id, name, show_completed, store FROM lists")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            GroceryList {
+                name: row.get(1)?,
+                items: vec![],
+                show_completed: row.get(2)?,
+                store: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (id, list) = row?;
+        lists.insert(id, list);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT list_id, id, name, completed, quantity, unit, notes, category FROM items
+         ORDER BY list_id, position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            Item {
+                id: row.get(1)?,
+                name: row.get(2)?,
+                completed: row.get(3)?,
+                quantity: row.get(4)?,
+                unit: row.get(5)?,
+                notes: row.get(6)?,
+                category: row.get(7)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (list_id, item) = row?;
+        if let Some(list) = lists.get_mut(&list_id) {
+            list.items.push(item);
+        }
+    }
+
+    Ok(lists)
+}
+
+/// Writes only the rows of `new` that differ from `old`.
+fn write_list_diff(
+    tx: &Transaction,
+    id: &str,
+    old: Option<&GroceryList>,
+    new: &GroceryList,
+) -> rusqlite::Result<()> {
+    let header_changed = old.is_none_or(|old| {
+        old.name != new.name || old.show_completed != new.show_completed || old.store != new.store
+    });
+    if header_changed {
+        tx.execute(
+            "INSERT INTO lists (id, name, show_completed, store) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name,
+                 show_completed = excluded.show_completed, store = excluded.store",
+            params![id, new.name, new.show_completed, new.store],
+        )?;
+    }
+
+    let old_items: HashMap<&str, (usize, &Item)> = old
+        .map(|old| {
+            old.items
+                .iter()
+                .enumerate()
+                .map(|(position, item)| (item.id.as_str(), (position, item)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (position, item) in new.items.iter().enumerate() {
+        if old_items.get(item.id.as_str()) != Some(&(position, item)) {
+            tx.execute(
+                "INSERT INTO items
+                     (id, list_id, position, name, completed, quantity, unit, notes, category)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(id) DO UPDATE SET list_id = excluded.list_id,
+                     position = excluded.position, name = excluded.name,
+                     completed = excluded.completed, quantity = excluded.quantity,
+                     unit = excluded.unit, notes = excluded.notes,
+                     category = excluded.category",
+                params![
+                    item.id,
+                    id,
+                    position,
+                    item.name,
+                    item.completed,
+                    item.quantity,
+                    item.unit,
+                    item.notes,
+                    item.category
+                ],
+            )?;
+        }
+    }
+
+    // An item that left for another list has already been written there.
+    for old_id in old_items.keys() {
+        if !new.items.iter().any(|item| item.id == *old_id) {
+            tx.execute(
+                "DELETE FROM items WHERE id = ?1 AND list_id = ?2",
+                params![old_id, id],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn import_json(tx: &Transaction, path: &Path) -> io::Result<()> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let mut lists: Lists = serde_json::from_str(&json)?;
+
+    for (id, list) in lists.iter_mut() {
+        list.assign_missing_ids();
+        write_list_diff(tx, id, None, list).map_err(to_io)?;
+    }
+    println!("Imported {} lists from {}", lists.len(), path.display());
+    Ok(())
+}
+
+fn to_io(err: rusqlite::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> GroceryList {
+        GroceryList {
+            name: "Groceries".into(),
+            items: items
+                .iter()
+                .map(|name| Item::new(name.to_string()))
+                .collect(),
+            show_completed: true,
+            store: None,
+        }
+    }
+
+    fn save(conn: &mut Connection, old: Option<&GroceryList>, new: &GroceryList) {
+        let tx = conn.transaction().unwrap();
+        write_list_diff(&tx, "groceries", old, new).unwrap();
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn writes_changed_rows_and_deletes_removed_ones() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS {
+            conn.execute_batch(migration).unwrap();
+        }
+
+        let old = list(&["milk", "eggs", "bread"]);
+        save(&mut conn, None, &old);
+        let mut new = old.clone();
+        new.items.remove(1);
+        new.items.swap(0, 1);
+        new.items[1].completed = true;
+        new.items.push(Item::new("butter".into()));
+        save(&mut conn, Some(&old), &new);
+
+        assert!(read_lists(&conn).unwrap()["groceries"] == new);
+    }
+
+    #[test]
+    fn imports_json_only_into_a_new_database() {
+        let dir = std::env::temp_dir().join(format!("yala-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (db, json) = (dir.join("lists.db"), dir.join("lists.json"));
+        let _ = std::fs::remove_file(&db);
+
+        let lists = Lists::from([("groceries".to_string(), list(&["milk"]))]);
+        std::fs::write(&json, serde_json::to_string(&lists).unwrap()).unwrap();
+        let store = SqliteStore::open(&db, &json).unwrap();
+        assert!(read_lists(&store.inner.lock().unwrap().conn).unwrap() == lists);
+        drop(store);
+
+        std::fs::write(&json, "{}").unwrap();
+        let store = SqliteStore::open(&db, &json).unwrap();
+        assert!(read_lists(&store.inner.lock().unwrap().conn).unwrap() == lists);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How many suggestions are offered at once.
+const LIMIT: usize = 6;
+
+/// Every item name ever added, with how often and how recently it was added
+/// and to which lists, so the add field can offer it again. Names are
+/// matched ignoring case and surrounding whitespace.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NameIndex(BTreeMap<String, NameStats>);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NameStats {
+    /// The name as it was last typed.
+    pub name: String,
+    pub count: u32,
+    /// When it was last added, in milliseconds since the epoch.
+    pub last_added: u64,
+    /// How often it was added to each list.
+    #[serde(default)]
+    pub lists: BTreeMap<String, u32>,
+}
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+impl NameIndex {
+    /// Notes that `name` was added to list `list_id` at `at`, and returns
+    /// its entry as it is now.
+    pub fn record(&mut self, list_id: &str, name: &str, at: u64) -> (String, NameStats) {
+        let key = key(name);
+        let stats = self.0.entry(key.clone()).or_insert_with(|| NameStats {
+            name: String::new(),
+            count: 0,
+            last_added: 0,
+            lists: BTreeMap::new(),
+        });
+        stats.name = name.trim().to_string();
+        stats.count += 1;
+        stats.last_added = stats.last_added.max(at);
+        *stats.lists.entry(list_id.to_string()).or_default() += 1;
+        (key, stats.clone())
+    }
+
+    /// Names matching `query`, best first. Names starting with it come
+    /// before names with a word starting with it, and those before names
+    /// that are only a typo or two away. Within each group, names added
+    /// often, lately and to list `list_id` itself win. Names in `skip`
+    /// are left out.
+    pub fn suggest(&self, list_id: &str, query: &str, skip: &[&str], now: u64) -> Vec<String> {
+        let query = key(query);
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let mut found: Vec<(u8, f64, &str)> = self
+            .0
+            .iter()
+            .filter(|(key, _)| **key != query && !skip.iter().any(|s| s.eq_ignore_ascii_case(key)))
+            .filter_map(|(key, stats)| {
+                let tier = matches(key, &query)?;
+                Some((tier, stats.score(list_id, now), stats.name.as_str()))
+            })
+            .collect();
+        found.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(b.1.total_cmp(&a.1))
+                .then_with(|| a.2.to_lowercase().cmp(&b.2.to_lowercase()))
+        });
+        found
+            .into_iter()
+            .take(LIMIT)
+            .map(|(_, _, name)| name.to_string())
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &NameStats)> {
+        self.0.iter()
+    }
+
+    pub fn insert(&mut self, key: String, stats: NameStats) {
+        self.0.insert(key, stats);
+    }
+}
+
+impl NameStats {
+    /// Adds to this list count four times as much as adds to other lists,
+    /// and the total fades to half within two weeks of the last add.
+    fn score(&self, list_id: &str, now: u64) -> f64 {
+        let here = self.lists.get(list_id).copied().unwrap_or(0);
+        let days = now.saturating_sub(self.last_added) as f64 / (24.0 * 60.0 * 60.0 * 1000.0);
+        (self.count + 4 * here) as f64 / (1.0 + days / 14.0)
+    }
+}
+
+/// How well `name` matches `query`, both lowercase: 3 when it starts with
+/// it, 2 when one of its words does, 1 when the start of a word is close
+/// enough to it to be a typo.
+fn matches(name: &str, query: &str) -> Option<u8> {
+    if name.starts_with(query) {
+        return Some(3);
+    }
+    let words: Vec<&str> = name
+        .char_indices()
+        .filter(|&(i, _)| i == 0 || name[..i].ends_with(' '))
+        .map(|(i, _)| &name[i..])
+        .collect();
+    if words.iter().any(|word| word.starts_with(query)) {
+        return Some(2);
+    }
+
+    let query: Vec<char> = query.chars().collect();
+    let allowed = match query.len() {
+        0..=2 => return None,
+        3..=5 => 1,
+        _ => 2,
+    };
+    // The query is usually only the start of the name, so it is compared
+    // with starts of about its own length.
+    let typo = words.iter().any(|word| {
+        let word: Vec<char> = word.chars().collect();
+        (query.len() - 1..=query.len() + 1)
+            .filter(|&len| len <= word.len())
+            .any(|len| distance(&query, &word[..len]) <= allowed)
+    });
+    typo.then_some(1)
+}
+
+/// Edits needed to turn `a` into `b`, where swapping two neighbouring
+/// letters counts as one.
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60 * 1000;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn counts_a_swap_of_neighbours_as_one_edit() {
+        assert_eq!(distance(&chars("milk"), &chars("milk")), 0);
+        assert_eq!(distance(&chars("mlik"), &chars("milk")), 1);
+        assert_eq!(distance(&chars("mulk"), &chars("milk")), 1);
+        assert_eq!(distance(&chars("mil"), &chars("milk")), 1);
+        assert_eq!(distance(&chars("bnaana"), &chars("banana")), 1);
+        assert_eq!(distance(&chars("kilm"), &chars("milk")), 2);
+    }
+
+    #[test]
+    fn ranks_starts_before_words_before_typos() {
+        let mut index = NameIndex::default();
+        for name in ["Tomatoes", "Cherry tomatoes", "Tomme", "Potatoes"] {
+            index.record("groceries", name, 0);
+        }
+        assert_eq!(
+            index.suggest("groceries", "tom", &[], 0),
+            ["Tomatoes", "Tomme", "Cherry tomatoes"]
+        );
+        assert_eq!(
+            index.suggest("groceries", "toma", &[], 0),
+            ["Tomatoes", "Cherry tomatoes", "Tomme"]
+        );
+        assert_eq!(index.suggest("groceries", "potatos", &[], 0), ["Potatoes"]);
+        assert_eq!(
+            index.suggest("groceries", "tomatoes", &["Cherry tomatoes"], 0),
+            ["Potatoes"]
+        );
+    }
+
+    #[test]
+    fn prefers_names_added_often_lately_and_to_this_list() {
+        let mut index = NameIndex::default();
+        index.record("hardware", "Batteries", 0);
+        index.record("groceries", "Bananas", 0);
+        assert_eq!(
+            index.suggest("groceries", "ba", &[], 0),
+            ["Bananas", "Batteries"]
+        );
+        assert_eq!(
+            index.suggest("hardware", "ba", &[], 0),
+            ["Batteries", "Bananas"]
+        );
+
+        index.record("groceries", "Bagels", 0);
+        index.record("groceries", "Bagels", 0);
+        index.record("groceries", "Basil", 60 * DAY);
+        assert_eq!(
+            index.suggest("groceries", "ba", &[], 60 * DAY),
+            ["Basil", "Bagels", "Bananas", "Batteries"]
+        );
+    }
+}
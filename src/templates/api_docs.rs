@@ -0,0 +1,173 @@
+use crate::assets::styles;
+use maud::{DOCTYPE, Markup, PreEscaped, html};
+use utoipa::openapi::{OpenApi, RefOr, path::Operation};
+
+/// Self-contained API explorer: everything is rendered from the OpenAPI
+/// document on the server, and the page loads nothing from other hosts.
+pub fn render(spec: &OpenApi, server: &str) -> Markup {
+    html! {
+        (DOCTYPE)
+        html {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { "yala API" }
+                style { (PreEscaped(styles::CSS)) (PreEscaped(CSS)) }
+            }
+            body {
+                div .container.api-docs {
+                    h1 .toptitle { (spec.info.title) " API " small { (spec.info.version) } }
+                    @if let Some(description) = &spec.info.description {
+                        p .api-muted { (description) }
+                    }
+                    p .api-muted {
+                        "Machine-readable document: "
+                        a href={ (server) "/openapi.json" } { (server) "/openapi.json" }
+                    }
+
+                    @for (path, item) in spec.paths.paths.iter() {
+                        @for (method, operation) in operations(item) {
+                            (endpoint(server, method, path, operation))
+                        }
+                    }
+
+                    @if let Some(components) = &spec.components {
+                        h2 .api-section { "Schemas" }
+                        @for (name, schema) in components.schemas.iter() {
+                            details .api-endpoint {
+                                summary { code { (name) } }
+                                pre { (serde_json::to_string_pretty(schema).unwrap_or_default()) }
+                            }
+                        }
+                    }
+                }
+                script { (PreEscaped(JS)) }
+            }
+        }
+    }
+}
+
+fn operations(
+    item: &utoipa::openapi::PathItem,
+) -> impl Iterator<Item = (&'static str, &Operation)> {
+    [
+        ("GET", &item.get),
+        ("POST", &item.post),
+        ("PUT", &item.put),
+        ("PATCH", &item.patch),
+        ("DELETE", &item.delete),
+    ]
+    .into_iter()
+    .filter_map(|(method, operation)| operation.as_ref().map(|op| (method, op)))
+}
+
+fn endpoint(server: &str, method: &str, path: &str, operation: &Operation) -> Markup {
+    let params = operation.parameters.as_deref().unwrap_or_default();
+    let body_schema = operation.request_body.as_ref().and_then(|body| {
+        body.content
+            .values()
+            .find_map(|content| match &content.schema {
+                Some(RefOr::Ref(reference)) => reference.ref_location.rsplit('/').next(),
+                _ => None,
+            })
+    });
+
+    html! {
+        details .api-endpoint {
+            summary {
+                span class={ "api-method api-" (method.to_lowercase()) } { (method) }
+                code { (path) }
+                @if let Some(summary) = &operation.summary {
+                    span .api-muted { " " (summary) }
+                }
+            }
+            @if let Some(description) = &operation.description {
+                p .api-muted { (description) }
+            }
+
+            form .api-try data-method=(method) data-path={ (server) (path) } {
+                @for param in params {
+                    label {
+                        code { (param.name) }
+                        @if let Some(description) = &param.description {
+                            span .api-muted { " " (description) }
+                        }
+                        input type="text" name=(param.name) required;
+                    }
+                }
+                @if let Some(schema) = body_schema {
+                    label {
+                        "Body " code { (schema) }
+                        textarea name="body" rows="3" { "{}" }
+                    }
+                }
+                button .btn.btn-primary type="submit" { "Send" }
+                pre .api-result hidden {}
+            }
+
+            ul .api-responses {
+                @for (status, response) in operation.responses.responses.iter() {
+                    li {
+                        code { (status) }
+                        @if let RefOr::T(response) = response {
+                            " " (response.description)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+const CSS: &str = r#"
+.api-docs { padding: 0 16px 48px; }
+.api-docs small { color: #6b7280; font-size: 14px; }
+.api-muted { color: #9ca3af; margin-bottom: 12px; }
+.api-docs a { color: #60a5fa; }
+.api-section { margin: 32px 0 12px; font-weight: 500; }
+.api-endpoint { background: #1f2937; border: 1px solid #374151; border-radius: 8px; margin-bottom: 8px; padding: 12px 16px; }
+.api-endpoint summary { cursor: pointer; display: flex; align-items: center; gap: 8px; flex-wrap: wrap; }
+.api-endpoint pre { overflow-x: auto; font-size: 13px; margin-top: 12px; }
+.api-method { font-size: 12px; font-weight: 600; border-radius: 4px; padding: 2px 6px; min-width: 56px; text-align: center; }
+.api-get { background: #1e3a8a; }
+.api-post { background: #065f46; }
+.api-put, .api-patch { background: #78350f; }
+.api-delete { background: #7f1d1d; }
+.api-try { margin-top: 12px; }
+.api-try label { display: block; margin-bottom: 8px; }
+.api-try input[type="text"], .api-try textarea { margin: 4px 0 0; padding: 8px; }
+.api-try textarea { width: 100%; background: #111827; color: #f3f4f6; border: 1px solid #374151; border-radius: 8px; font-family: monospace; }
+.api-try button { padding: 8px; }
+.api-responses { margin: 12px 0 0 20px; color: #9ca3af; }
+"#;
+
+const JS: &str = r#"
+document.querySelectorAll('.api-try').forEach(function(form) {
+    form.addEventListener('submit', function(event) {
+        event.preventDefault();
+        var path = form.getAttribute('data-path');
+        form.querySelectorAll('input').forEach(function(input) {
+            path = path.replace('{' + input.name + '}', encodeURIComponent(input.value));
+        });
+        var options = { method: form.getAttribute('data-method'), headers: {} };
+        var body = form.querySelector('textarea');
+        if (body) {
+            options.headers['Content-Type'] = 'application/json';
+            options.body = body.value;
+        }
+        var result = form.querySelector('.api-result');
+        result.hidden = false;
+        result.textContent = '…';
+        fetch(path, options).then(function(response) {
+            return response.text().then(function(text) {
+                try {
+                    text = JSON.stringify(JSON.parse(text), null, 2);
+                } catch (e) {}
+                result.textContent = response.status + ' ' + response.statusText + '\n\n' + text;
+            });
+        }).catch(function(error) {
+            result.textContent = String(error);
+        });
+    });
+});
+"#;
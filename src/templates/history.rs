@@ -0,0 +1,106 @@
+use crate::changelog::{self, Change};
+use crate::models::GroceryList;
+use crate::templates::layout::ago;
+use maud::{Markup, html};
+
+/// Read-only list of the changes made to list `id`, most recent first.
+pub fn render(id: &str, name: &str, changes: &[Change]) -> Markup {
+    let now = changelog::now_ms();
+
+    html! {
+        div .container {
+            div .header {
+                a .back-btn href={"/list/" (id)} { "←" }
+                h1 { "History of " (name) }
+                div style="width: 40px;" {}
+            }
+
+            @if changes.is_empty() {
+                p .trash-note { "No changes have been recorded yet." }
+            }
+
+            @for change in changes {
+                a .history-entry href={"/list/" (id) "/history?at=" (change.at)} {
+                    span { span .history-who { (who(&change.client_id)) } " " (describe(change)) }
+                    span .trash-meta { (ago(now.saturating_sub(change.at) / 1000)) }
+                }
+            }
+        }
+    }
+}
+
+/// List `id` as it was at `at`, rebuilt from the log.
+pub fn render_at(id: &str, list: Option<&GroceryList>, at: u64) -> Markup {
+    let age = ago(changelog::now_ms().saturating_sub(at) / 1000);
+
+    html! {
+        div .container {
+            div .header {
+                a .back-btn href={"/list/" (id) "/history"} { "←" }
+                h1 {
+                    @match list {
+                        Some(list) => { (list.name) },
+                        None => { "Deleted list" },
+                    }
+                }
+                div style="width: 40px;" {}
+            }
+
+            p .trash-note { "As it was " (age) "." }
+
+            @if let Some(list) = list {
+                @for item in &list.items {
+                    div .history-item.completed[item.completed] {
+                        div .checkbox.checked[item.completed] {}
+                        span .item-text { (item.name) }
+                        @if let Some(amount) = item.amount() {
+                            span .quantity-badge { (amount) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Clients are only known by the id the page makes up. Its part before the
+/// `.` is kept on the device, so its start tells devices apart.
+fn who(client_id: &str) -> String {
+    let device = client_id.split('.').next().unwrap_or_default();
+    match device.get(..6) {
+        Some(short) => format!("Device {}", short),
+        None => "Someone".to_string(),
+    }
+}
+
+fn describe(change: &Change) -> String {
+    let detail = &change.detail;
+    match change.op.as_str() {
+        "create_list" => "created the list".to_string(),
+        "create_from_template" => format!("created the list from the template “{}”", detail),
+        "duplicate_list" => format!("created the list as a copy of “{}”", detail),
+        "update_list" if detail.is_empty() => "changed the list's settings".to_string(),
+        "update_list" => format!("renamed the list to “{}”", detail),
+        "toggle_show_completed" => format!("chose to {}", detail),
+        "delete_list" => "deleted the list".to_string(),
+        "add_item" | "bulk_add" => format!("added “{}”", detail),
+        "merge_item" => format!("added to “{}” already on the list", detail),
+        "merge_duplicates" => format!("merged duplicates: {}", detail),
+        "update_item" => format!("edited “{}”", detail),
+        "check_item" => format!("checked off “{}”", detail),
+        "uncheck_item" => format!("unchecked “{}”", detail),
+        "change_quantity" => format!("changed the quantity to “{}”", detail),
+        "delete_item" => format!("deleted “{}”", detail),
+        "clear_completed" => format!("deleted completed items: {}", detail),
+        "add_items" => format!("added “{}” from another list", detail),
+        "move_items" => format!("moved “{}” to another list", detail),
+        "move_item" => format!("moved “{}”", detail),
+        "sort_list" if detail.is_empty() => "sorted the list".to_string(),
+        "sort_list" => format!("sorted the list {}", detail),
+        "undo" => format!("undid “{}”", detail),
+        "redo" => format!("redid “{}”", detail),
+        "restore" => format!("restored “{}” from the trash", detail),
+        "external_edit" => "edited the data file".to_string(),
+        op => op.replace('_', " "),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::GroceryList;
+use crate::models::{GroceryList, ListTemplate};
 use maud::{Markup, html};
 use std::collections::HashMap;
 
@@ -29,6 +29,7 @@ fn empty_state() -> Markup {
                         span { "Create list" }
                     }
                 }
+                (trash_link())
             }
         }
     }
@@ -39,25 +40,65 @@ pub fn lists_view(lists: &HashMap<String, GroceryList>) -> Markup {
         h1 class="toptitle" { "Lists" }
         div .container {
             @for (id, list) in lists.iter() {
-                a .list-item href={"/list/" (id)} {
-                    span .list-name { (list.name) }
-                    div style="display: flex; align-items: center; gap: 12px;" {
-                        span .item-count { (list.items.len()) }
-                        span .arrow { "›" }
-                    }
-                }
+                (list_entry(id, list))
             }
             a .fab href="/new" { "+" }
+            (trash_link())
         }
     }
 }
 
-pub fn new_list_form() -> Markup {
+fn trash_link() -> Markup {
+    html! {
+        a .trash-link href="/trash" { "Trash" }
+    }
+}
+
+pub fn list_entry(id: &str, list: &GroceryList) -> Markup {
+    html! {
+        a .list-item href={"/list/" (id)} data-list-id=(id) {
+            span .list-name { (list.name) }
+            div style="display: flex; align-items: center; gap: 12px;" {
+                span .item-count { (list.items.len()) }
+                span .arrow { "›" }
+            }
+        }
+    }
+}
+
+/// The form for a new list. With templates saved, it also offers to start
+/// from one of them; picking one fills in its name if none was typed yet.
+pub fn new_list_form(templates: &[ListTemplate]) -> Markup {
     html! {
         div .container {
             h1 class="toptitle" { "Name your list" }
             form hx-post="/create" hx-target="body" {
                 input type="text" name="name" placeholder="Enter list name" required autofocus;
+                @if !templates.is_empty() {
+                    div .template-picker {
+                        div .template-picker-title { "Start from" }
+                        label .template-option {
+                            input type="radio" name="template" value="" checked;
+                            span .template-name { "Empty list" }
+                        }
+                        @for template in templates {
+                            label .template-option {
+                                input type="radio" name="template" value=(template.id) data-name=(template.name)
+                                    onchange="var name = this.form.elements['name']; if (!name.value) name.value = this.dataset.name";
+                                span .template-name { (template.name) }
+                                span .item-count { (template.items.len()) }
+                                button .template-delete type="button" aria-label="Delete template"
+                                    hx-post={"/templates/" (template.id) "/delete"} hx-target="body" {
+                                    "×"
+                                }
+                            }
+                        }
+                        label .template-reset {
+                            input type="checkbox" name="reset_completed" value="true" checked;
+                            span { "Uncheck everything" }
+                        }
+                    }
+                }
                 button .btn.btn-primary type="submit" { "Create list" }
             }
         }
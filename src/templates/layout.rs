@@ -21,3 +21,18 @@ pub fn render(content: Markup) -> Markup {
         }
     }
 }
+
+/// Formats a duration in seconds as "5 minutes ago".
+pub fn ago(secs: u64) -> String {
+    let (n, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    if n == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", n, unit)
+    }
+}
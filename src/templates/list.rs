@@ -1,59 +1,378 @@
-use crate::models::GroceryList;
+use crate::events::Action;
+use crate::models::{self, GroceryList, Item, StoreLayout};
+use crate::purchases::{self, Purchase};
+use crate::quantity;
 use maud::{Markup, PreEscaped, html};
 
-pub fn render(id: &str, list: &GroceryList) -> Markup {
+/// The list page. In shopping mode sections follow the walk path of the
+/// list's store, and sections with nothing left to buy are collapsed.
+pub fn render(
+    id: &str,
+    list: &GroceryList,
+    layouts: &[StoreLayout],
+    purchases: &[Purchase],
+    shopping: bool,
+) -> Markup {
     let visible_items: Vec<_> = list
         .items
         .iter()
-        .enumerate()
-        .filter(|(_, item)| list.show_completed || !item.completed)
+        .filter(|item| list.show_completed || !item.completed)
         .collect();
+    let layout = list
+        .store
+        .as_deref()
+        .and_then(|store| layouts.iter().find(|layout| layout.id == store));
+
+    let mut sections = sections(&visible_items);
+    if shopping {
+        sections.sort_by_key(|(category, _)| models::category_order(layout, *category));
+    }
 
     let hide_completed_attr = if list.show_completed { "false" } else { "true" };
 
     html! {
-        div .container data-hide-completed=(hide_completed_attr) {
+        div .container data-list-id=(id) data-hide-completed=(hide_completed_attr) data-shopping[shopping] {
             div .header {
                 a .back-btn href="/" { "←" }
                 h1 { (list.name) }
                 button .menu-btn onclick="document.getElementById('menu').style.display='block'" { "⋮" }
             }
 
-            @for (idx, item) in visible_items {
-                @let item_class = if item.completed { "item completed" } else { "item" };
-                @let checkbox_class = if item.completed { "checkbox checked" } else { "checkbox" };
-                @let delete_url = format!("/list/{}/delete-item/{}", id, idx);
-                @let edit_call = format!("window.editItem(this, '{}', {})", id, idx);
-                @let checkbox_click = format!("window.handleCheckboxClick(event, '{}', {})", id, idx);
-                div class=(item_class) data-delete-url=(delete_url) {
-                    div class=(checkbox_class)
-                        onclick=(PreEscaped(&checkbox_click)) {}
-                    span .item-text onclick=(PreEscaped(&edit_call)) { (item.name) }
+            @if shopping {
+                div .shopping-bar {
+                    span {
+                        @match layout {
+                            Some(layout) => { "Shopping at " b { (layout.name) } },
+                            None => { "Shopping" },
+                        }
+                    }
+                    button .shopping-done hx-post={"/list/" (id) "/shopping"} hx-target="body" { "Done" }
+                }
+            }
+
+            @if visible_items.iter().any(|item| item.category.is_some()) {
+                @for (category, items) in sections {
+                    @if shopping && items.iter().all(|item| item.completed) {
+                        details .section.done data-category=(category.unwrap_or_default()) {
+                            summary .section-header {
+                                (category.unwrap_or("Other")) " · all " (items.len()) " done"
+                            }
+                            @for item in items {
+                                (self::item(id, item))
+                            }
+                        }
+                    } @else {
+                        div .section data-category=(category.unwrap_or_default()) {
+                            div .section-header { (category.unwrap_or("Other")) }
+                            @for item in items {
+                                (self::item(id, item))
+                            }
+                        }
+                    }
+                }
+            } @else {
+                @for item in visible_items {
+                    (self::item(id, item))
                 }
             }
 
             div .add-item {
                 form hx-post={"/list/" (id) "/add"} hx-target="body" hx-swap="outerHTML" {
                     div .checkbox {}
-                    input #add-input type="text" name="item" placeholder="Add item" required;
+                    input #add-input type="text" name="item" placeholder="Add item" autocomplete="off" required
+                        hx-get={"/list/" (id) "/suggestions"} hx-trigger="input changed delay:150ms"
+                        hx-target="#suggestions" hx-swap="innerHTML"
+                        onblur="window.clearSuggestions()";
                 }
             }
+            div #suggestions .suggestions {}
+            (buy_again(id, list, purchases))
 
-            (menu(id, list.show_completed))
+            div #details-slot {}
+            (menu(id, list.show_completed, layout, shopping))
+            (store_picker(id, layouts, layout))
+            (rename_modal(id, &list.name))
+            (save_template_modal(id, &list.name))
+            (duplicate_modal(id, &list.name))
             (confirm_modal(id))
         }
     }
 }
 
-fn menu(id: &str, show_completed: bool) -> Markup {
+/// Groups items by category, in the order each category first appears.
+/// Items without a category come last.
+fn sections<'a>(items: &[&'a Item]) -> Vec<(Option<&'a str>, Vec<&'a Item>)> {
+    let mut sections: Vec<(Option<&str>, Vec<&Item>)> = vec![];
+    for item in items {
+        let category = item.category.as_deref();
+        match sections.iter_mut().find(|(c, _)| *c == category) {
+            Some((_, items)) => items.push(item),
+            None => sections.push((category, vec![item])),
+        }
+    }
+    sections.sort_by_key(|(category, _)| category.is_none());
+    sections
+}
+
+pub fn item(list_id: &str, item: &Item) -> Markup {
+    let item_class = if item.completed {
+        "item completed"
+    } else {
+        "item"
+    };
+    let checkbox_class = if item.completed {
+        "checkbox checked"
+    } else {
+        "checkbox"
+    };
+    let delete_url = format!("/list/{}/delete-item/{}", list_id, item.id);
+    let edit_call = format!("window.editItem(this, '{}', '{}')", list_id, item.id);
+    let checkbox_click = format!(
+        "window.handleCheckboxClick(event, '{}', '{}')",
+        list_id, item.id
+    );
+    let step = |direction: &str| {
+        format!(
+            "window.stepQuantity('{}', '{}', '{}')",
+            list_id, item.id, direction
+        )
+    };
+    let details_call = format!("window.openDetails('{}', '{}')", list_id, item.id);
+    let amount = item.amount();
+    // The edit field starts out with the quantity in front of the name.
+    let edit_text = match &amount {
+        Some(amount) => format!("{} {}", amount, item.name),
+        None => item.name.clone(),
+    };
+
+    html! {
+        div class=(item_class) data-item-id=(item.id) data-delete-url=(delete_url) {
+            span .drag-handle aria-label="Move" { "⠿" }
+            div class=(checkbox_class)
+                onclick=(PreEscaped(&checkbox_click)) {}
+            div .item-main {
+                span .item-text data-edit=(edit_text) onclick=(PreEscaped(&edit_call)) { (item.name) }
+                @if let Some(notes) = &item.notes {
+                    span .item-notes { (notes) }
+                }
+            }
+            div .quantity {
+                @if let Some(amount) = amount {
+                    button .quantity-step onclick=(PreEscaped(&step("decrement"))) aria-label="Less" { "−" }
+                    span .quantity-badge { (amount) }
+                }
+                button .quantity-step onclick=(PreEscaped(&step("increment"))) aria-label="More" { "+" }
+            }
+            button .details-btn onclick=(PreEscaped(&details_call)) aria-label="Details" { "›" }
+        }
+    }
+}
+
+/// Sheet for editing everything about an item at once, loaded into
+/// `#details-slot` when the item is long-pressed or its chevron tapped.
+pub fn details(list_id: &str, item: &Item, categories: &[String]) -> Markup {
+    let quantity = item.quantity.map(quantity::format_number);
+
+    html! {
+        div .modal onclick="if (event.target === this) window.closeDetails()" {
+            div .modal-content {
+                div .modal-title { "Item details" }
+                form hx-post={"/list/" (list_id) "/details/" (item.id)} hx-target="body" {
+                    input type="text" name="name" value=(item.name) placeholder="Name" required;
+                    div .details-row {
+                        input type="text" name="quantity" inputmode="decimal" value=[quantity] placeholder="Quantity";
+                        input type="text" name="unit" value=[item.unit.as_deref()] placeholder="Unit";
+                    }
+                    input type="text" name="category" list="categories" value=[item.category.as_deref()] placeholder="Category";
+                    datalist #categories {
+                        @for category in categories {
+                            option value=(category) {}
+                        }
+                    }
+                    textarea name="notes" rows="3" placeholder="Notes" { (item.notes.as_deref().unwrap_or_default()) }
+                    button .btn.btn-primary type="submit" { "Save" }
+                }
+                a .trash-link href="#" hx-get={"/list/" (list_id) "/move-to/" (item.id)} hx-target="#details-slot" { "Move to…" }
+            }
+        }
+    }
+}
+
+/// Sheet showing how pasted text is split into items before they are
+/// added. Editing the text updates the preview.
+pub fn bulk_preview(list_id: &str, text: &str, items: &[Item]) -> Markup {
+    html! {
+        div .modal onclick="if (event.target === this) window.closeDetails()" {
+            div .modal-content {
+                div .modal-title { "Add several items" }
+                form hx-post={"/list/" (list_id) "/add-items"} hx-target="body" {
+                    textarea name="item" rows="6"
+                        hx-post={"/list/" (list_id) "/preview"} hx-trigger="input changed delay:300ms"
+                        hx-target="#bulk-items" hx-select="#bulk-items" hx-swap="outerHTML" { (text) }
+                    div #bulk-items {
+                        ul .bulk-items {
+                            @for item in items {
+                                li {
+                                    span { (item.name) }
+                                    @if let Some(amount) = item.amount() {
+                                        span .quantity-badge { (amount) }
+                                    }
+                                }
+                            }
+                        }
+                        button .btn.btn-primary type="submit" disabled[items.is_empty()] {
+                            @match items.len() {
+                                1 => "Add 1 item",
+                                n => { "Add " (n) " items" },
+                            }
+                        }
+                        // Commas may as well be part of a name, as in
+                        // "Tomatoes, canned".
+                        @if items.len() > 1 && !text.trim().contains('\n') {
+                            button .btn.bulk-single type="button"
+                                hx-post={"/list/" (list_id) "/add"} hx-target="body" {
+                                "Add as one item"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Things bought from the list before that are not on it now, to put back
+/// with one tap.
+fn buy_again(id: &str, list: &GroceryList, purchases: &[Purchase]) -> Markup {
+    let purchases = purchases::not_on(list, purchases);
+
+    html! {
+        @if !purchases.is_empty() {
+            details .buy-again {
+                summary .section-header { "Buy again · " (purchases.len()) }
+                div .buy-again-items {
+                    @for purchase in purchases {
+                        button .buy-again-item data-name=(purchase.name.to_lowercase())
+                            hx-post={"/list/" (id) "/buy-again"}
+                            hx-vals=(serde_json::json!({ "item": purchase.name }).to_string())
+                            hx-target="body" {
+                            (purchase.name)
+                            @if let Some(q) = purchase.quantity {
+                                span .buy-again-amount { (quantity::format(q, purchase.unit.as_deref())) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Names offered under the add field. `amount` is a quantity typed in
+/// front of the name, kept when a suggestion is picked.
+pub fn suggestions(names: &[String], amount: Option<&str>) -> Markup {
+    html! {
+        @for name in names {
+            @let value = match amount {
+                Some(amount) => format!("{} {}", amount, name),
+                None => name.clone(),
+            };
+            // Pressing keeps the focus in the add field, so the list does
+            // not close before the click lands.
+            div .suggestion data-value=(value) onmousedown="event.preventDefault()"
+                onclick="window.pickSuggestion(this)" { (name) }
+        }
+    }
+}
+
+/// Picker for the list to move or copy an item to, loaded into
+/// `#details-slot` from the details sheet.
+pub fn transfer_picker(list_id: &str, item: &Item, lists: &[(&String, &String)]) -> Markup {
+    let vals = |to: &str| format!(r#"{{"to": "{}"}}"#, to);
+
+    html! {
+        div .modal onclick="if (event.target === this) window.closeDetails()" {
+            div .modal-content {
+                div .modal-title { "Move “" (item.name) "” to…" }
+                @if lists.is_empty() {
+                    p .trash-note { "There are no other lists yet." }
+                }
+                @for (id, name) in lists {
+                    div .transfer-option {
+                        span .transfer-name { (name) }
+                        button .trash-action hx-post={"/list/" (list_id) "/move-to/" (item.id)} hx-vals=(vals(id)) hx-target="body" { "Move" }
+                        button .trash-action hx-post={"/list/" (list_id) "/copy-to/" (item.id)} hx-vals=(vals(id)) hx-target="body" { "Copy" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn toast(list_id: &str, message: &str, action: Action) -> Markup {
+    let (label, path) = match action {
+        Action::Undo => ("Undo", "undo"),
+        Action::Redo => ("Redo", "redo"),
+    };
+    let url = format!("/list/{}/{}", list_id, path);
+    let click = format!("window.runToastAction('{}')", url);
+
+    html! {
+        div .toast data-list-id=(list_id) {
+            span { (message) }
+            button .toast-action onclick=(PreEscaped(&click)) { (label) }
+        }
+    }
+}
+
+fn menu(id: &str, show_completed: bool, layout: Option<&StoreLayout>, shopping: bool) -> Markup {
     html! {
         div #menu .menu style="display:none;" {
+            div .menu-item hx-post={"/list/" (id) "/shopping"} hx-target="body" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M3 3h2l.4 2M7 13h10l4-8H5.4M7 13L5.4 5M7 13l-2.293 2.293c-.63.63-.184 1.707.707 1.707H17m0 0a2 2 0 100 4 2 2 0 000-4zm-8 2a2 2 0 11-4 0 2 2 0 014 0z" {}
+                }
+                @if shopping {
+                    span { "Stop shopping" }
+                } @else {
+                    span { "Start shopping" }
+                }
+            }
+            div .menu-item onclick="document.getElementById('store-picker').style.display='flex';document.getElementById('menu').style.display='none';" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M19 21V5a2 2 0 00-2-2H7a2 2 0 00-2 2v16m14 0h2m-2 0h-5m-9 0H3m2 0h5M9 7h1m-1 4h1m4-4h1m-1 4h1m-5 10v-5a1 1 0 011-1h2a1 1 0 011 1v5m-4 0h4" {}
+                }
+                @match layout {
+                    Some(layout) => span { "Store: " (layout.name) },
+                    None => span { "Choose store" },
+                }
+            }
             div .menu-item hx-post={"/list/" (id) "/sort"} hx-target="body" {
                 svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                     path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M3 4h13M3 8h9m-9 4h6m4 0l4-4m0 0l4 4m-4-4v12" {}
                 }
                 span { "Sort A-Z" }
             }
+            div .menu-item hx-post={"/list/" (id) "/sort?by=category"} hx-target="body" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M4 6h16M4 12h16M4 18h7" {}
+                }
+                span { "Sort by category" }
+            }
+            @if layout.is_some() {
+                div .menu-item hx-post={"/list/" (id) "/sort?by=store"} hx-target="body" {
+                    svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                        path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M9 20l-5.447-2.724A1 1 0 013 16.382V5.618a1 1 0 011.447-.894L9 7m0 13l6-3m-6 3V7m6 10l4.553 2.276A1 1 0 0021 18.382V7.618a1 1 0 00-.553-.894L15 4m0 13V4m0 0L9 7" {}
+                    }
+                    span { "Sort by store" }
+                }
+            }
+            div .menu-item hx-post={"/list/" (id) "/merge-duplicates"} hx-target="body" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M8 7v8a2 2 0 002 2h6M8 7V5a2 2 0 012-2h4.586a1 1 0 01.707.293l4.414 4.414a1 1 0 01.293.707V15a2 2 0 01-2 2h-2M8 7H6a2 2 0 00-2 2v10a2 2 0 002 2h8a2 2 0 002-2v-2" {}
+                }
+                span { "Merge duplicates" }
+            }
             div .menu-item onclick=(PreEscaped(&format!("window.handleToggleCompleted('{}')", id))) {
                 svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                     path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M15 12a3 3 0 11-6 0 3 3 0 016 0z" {}
@@ -71,6 +390,30 @@ fn menu(id: &str, show_completed: bool) -> Markup {
                 }
                 span { "Delete completed items" }
             }
+            div .menu-item onclick="document.getElementById('rename').style.display='flex';document.getElementById('menu').style.display='none';" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M11 5H6a2 2 0 00-2 2v11a2 2 0 002 2h11a2 2 0 002-2v-5m-1.414-9.414a2 2 0 112.828 2.828L11.828 15H9v-2.828l8.586-8.586z" {}
+                }
+                span { "Rename list" }
+            }
+            div .menu-item onclick="document.getElementById('save-template').style.display='flex';document.getElementById('menu').style.display='none';" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M5 5a2 2 0 012-2h10a2 2 0 012 2v16l-7-3.5L5 21V5z" {}
+                }
+                span { "Save as template" }
+            }
+            div .menu-item onclick="document.getElementById('duplicate').style.display='flex';document.getElementById('menu').style.display='none';" {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M8 16H6a2 2 0 01-2-2V6a2 2 0 012-2h8a2 2 0 012 2v2m-6 12h8a2 2 0 002-2v-8a2 2 0 00-2-2h-8a2 2 0 00-2 2v8a2 2 0 002 2z" {}
+                }
+                span { "Duplicate list" }
+            }
+            a .menu-item href={"/list/" (id) "/history"} {
+                svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
+                    path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M12 8v4l3 3m6-3a9 9 0 11-18 0 9 9 0 0118 0z" {}
+                }
+                span { "History" }
+            }
             div .menu-item.danger onclick="document.getElementById('confirm').style.display='flex';document.getElementById('menu').style.display='none';" {
                 svg fill="none" stroke="currentColor" viewBox="0 0 24 24" {
                     path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M19 7l-.867 12.142A2 2 0 0116.138 21H7.862a2 2 0 01-1.995-1.858L5 7m5 4v6m4-6v6m1-10V4a1 1 0 00-1-1h-4a1 1 0 00-1 1v3M4 7h16" {}
@@ -81,6 +424,77 @@ fn menu(id: &str, show_completed: bool) -> Markup {
     }
 }
 
+fn store_picker(id: &str, layouts: &[StoreLayout], selected: Option<&StoreLayout>) -> Markup {
+    let selected = selected.map(|layout| layout.id.as_str());
+
+    html! {
+        div #store-picker .modal style="display:none;" onclick="if (event.target === this) this.style.display='none'" {
+            div .modal-content {
+                div .modal-title { "Which store?" }
+                @for layout in layouts {
+                    button .store-option.selected[selected == Some(layout.id.as_str())]
+                        hx-post={"/list/" (id) "/store"} hx-vals=(format!(r#"{{"store": "{}"}}"#, layout.id)) hx-target="body" {
+                        (layout.name)
+                    }
+                }
+                button .store-option.selected[selected.is_none()]
+                    hx-post={"/list/" (id) "/store"} hx-vals=r#"{"store": ""}"# hx-target="body" {
+                    "No store"
+                }
+                a .trash-link href="/stores" { "Manage stores" }
+            }
+        }
+    }
+}
+
+fn rename_modal(id: &str, name: &str) -> Markup {
+    html! {
+        div #rename .modal style="display:none;" onclick="if (event.target === this) this.style.display='none'" {
+            div .modal-content {
+                div .modal-title { "Rename list" }
+                form hx-post={"/list/" (id) "/rename"} hx-target="body" {
+                    input type="text" name="name" value=(name) required;
+                    button .btn.btn-primary type="submit" { "Save" }
+                }
+            }
+        }
+    }
+}
+
+/// Saving under the name of an existing template replaces it.
+fn save_template_modal(id: &str, name: &str) -> Markup {
+    html! {
+        div #save-template .modal style="display:none;" onclick="if (event.target === this) this.style.display='none'" {
+            div .modal-content {
+                div .modal-title { "Save as template" }
+                form hx-post={"/list/" (id) "/save-template"} hx-target="body" {
+                    input type="text" name="name" value=(name) required;
+                    button .btn.btn-primary type="submit" { "Save template" }
+                }
+                a .trash-link href="/new" { "New list from a template" }
+            }
+        }
+    }
+}
+
+fn duplicate_modal(id: &str, name: &str) -> Markup {
+    html! {
+        div #duplicate .modal style="display:none;" onclick="if (event.target === this) this.style.display='none'" {
+            div .modal-content {
+                div .modal-title { "Duplicate list" }
+                form hx-post={"/list/" (id) "/duplicate"} hx-target="body" {
+                    input type="text" name="name" value={(name) " (copy)"} required;
+                    label .template-reset {
+                        input type="checkbox" name="reset_completed" value="true" checked;
+                        span { "Uncheck everything" }
+                    }
+                    button .btn.btn-primary type="submit" { "Duplicate" }
+                }
+            }
+        }
+    }
+}
+
 fn confirm_modal(id: &str) -> Markup {
     html! {
         div #confirm .modal style="display:none;" {
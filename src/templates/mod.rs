@@ -1,3 +1,7 @@
+pub mod api_docs;
+pub mod history;
 pub mod home;
 pub mod layout;
 pub mod list;
+pub mod stores;
+pub mod trash;
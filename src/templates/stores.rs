@@ -0,0 +1,45 @@
+use crate::models::StoreLayout;
+use maud::{Markup, html};
+
+/// Store layouts, each editable in place, and a form for a new one.
+/// `categories` are the ones items have been filed under, as a reminder of
+/// what to put in a walk path.
+pub fn render(layouts: &[StoreLayout], categories: &[String]) -> Markup {
+    html! {
+        div .container {
+            div .header {
+                a .back-btn href="/" { "←" }
+                h1 { "Stores" }
+                div style="width: 40px;" {}
+            }
+
+            p .trash-note {
+                "List the categories in the order you pass them in the store, one per line. "
+                "Lists shopped there follow that order in shopping mode."
+            }
+            @if !categories.is_empty() {
+                p .trash-note { "Categories in use: " (categories.join(", ")) }
+            }
+
+            @for layout in layouts {
+                form .store-layout hx-post={"/stores/" (layout.id)} hx-target="body" {
+                    input type="text" name="name" value=(layout.name) required;
+                    textarea name="categories" rows="6" placeholder="Categories, one per line" {
+                        (layout.categories.join("\n"))
+                    }
+                    div .details-row {
+                        button .btn.btn-primary type="submit" { "Save" }
+                        button .btn.btn-danger type="button" hx-post={"/stores/" (layout.id) "/delete"} hx-target="body" { "Delete" }
+                    }
+                }
+            }
+
+            form .store-layout hx-post="/stores" hx-target="body" {
+                div .modal-title { "New store" }
+                input type="text" name="name" placeholder="Name" required;
+                textarea name="categories" rows="6" placeholder="Categories, one per line" {}
+                button .btn.btn-primary type="submit" { "Add store" }
+            }
+        }
+    }
+}
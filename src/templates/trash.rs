@@ -0,0 +1,74 @@
+use crate::models::{self, TrashEntry, Trashed};
+use crate::store::Lists;
+use crate::templates::layout::ago;
+use maud::{Markup, html};
+use std::time::Duration;
+
+pub fn render(trash: &[TrashEntry], lists: &Lists, retention: Option<Duration>) -> Markup {
+    let now = models::now();
+
+    html! {
+        div .container {
+            div .header {
+                a .back-btn href="/" { "←" }
+                h1 { "Trash" }
+                div style="width: 40px;" {}
+            }
+
+            @if let Some(retention) = retention {
+                p .trash-note {
+                    "Deleted lists and items are removed for good after "
+                    (retention.as_secs() / (24 * 60 * 60)) " days."
+                }
+            }
+
+            @if trash.is_empty() {
+                p .trash-note { "The trash is empty." }
+            }
+
+            @for entry in trash {
+                div .trash-entry {
+                    div .trash-details {
+                        span .list-name { (entry.name()) }
+                        span .trash-meta {
+                            (origin(entry, lists)) " · deleted " (ago(now.saturating_sub(entry.deleted_at)))
+                        }
+                    }
+                    button .trash-action hx-post={"/trash/" (entry.id) "/restore"} hx-target="body" { "Restore" }
+                    button .trash-action.danger hx-post={"/trash/" (entry.id) "/purge"} hx-target="body" { "Delete" }
+                }
+            }
+
+            @if !trash.is_empty() {
+                button .btn.btn-danger onclick="document.getElementById('confirm').style.display='flex'" { "Empty trash" }
+                (confirm_modal())
+            }
+        }
+    }
+}
+
+/// Describes what was deleted: a list with its size, or an item with the
+/// list it came from.
+fn origin(entry: &TrashEntry, lists: &Lists) -> String {
+    match &entry.content {
+        Trashed::List { list } => match list.items.len() {
+            1 => "List with 1 item".to_string(),
+            n => format!("List with {} items", n),
+        },
+        Trashed::Item { .. } => match lists.get(&entry.list_id) {
+            Some(list) => format!("Item from {}", list.name),
+            None => "Item from a deleted list".to_string(),
+        },
+    }
+}
+
+fn confirm_modal() -> Markup {
+    html! {
+        div #confirm .modal style="display:none;" onclick="if (event.target === this) this.style.display='none'" {
+            div .modal-content {
+                div .modal-title { "Delete everything in the trash for good?" }
+                button .btn.btn-danger hx-post="/trash/empty" hx-target="body" { "Yes, empty trash" }
+            }
+        }
+    }
+}